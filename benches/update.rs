@@ -0,0 +1,73 @@
+//! Ticks per second of `World::update` on a large world, sequential and
+//! striped with a growing number of threads.
+//!
+//! `cargo bench --bench update -- [TICKS]`
+
+use std::time::Instant;
+
+use evocell::{config::SimConfig, math::Position, world::World};
+
+const STRIPE_HEIGHT: i32 = 16;
+/// Ticks run before the measure, so the population has grown
+const WARM_UP: u64 = 200;
+
+fn populated(stripe_height: i32, threads: usize) -> World {
+    let config = SimConfig {
+        seed: Some(42),
+        radius_petri_dish: 256,
+        width: 1024,
+        stripe_height,
+        ..SimConfig::default()
+    };
+    let mut world = World::new(config);
+    world
+        .set_threads(threads)
+        .expect("failed to start the workers");
+    for x in (2..config.width).step_by(4) {
+        for y in (2..config.height()).step_by(4) {
+            let cell = world.new_cell();
+            world.spawn(Position::new(x, y), cell);
+        }
+    }
+    for _ in 0..WARM_UP {
+        world.update();
+    }
+    world
+}
+
+/// Ticks per second
+fn measure(mut world: World, ticks: u64) -> f64 {
+    let start = Instant::now();
+    for _ in 0..ticks {
+        world.update();
+    }
+    ticks as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let ticks = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(200);
+    let cores = std::thread::available_parallelism().map_or(1, |cores| cores.get());
+
+    let sequential = measure(populated(0, 1), ticks);
+    println!("sequential           {:>8.1} ticks/s", sequential);
+
+    let mut threads = 1;
+    let mut single = None;
+    loop {
+        let tps = measure(populated(STRIPE_HEIGHT, threads), ticks);
+        let single = *single.get_or_insert(tps);
+        println!(
+            "stripes, {:>2} threads {:>8.1} ticks/s  x{:.2}",
+            threads,
+            tps,
+            tps / single
+        );
+        if threads >= cores {
+            break;
+        }
+        threads = (threads * 2).min(cores);
+    }
+}
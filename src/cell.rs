@@ -1,19 +1,30 @@
-use std::collections::HashSet;
-
 use rand::Rng;
+use serde::{Deserialize, Serialize};
+use variant_count::VariantCount;
 
 use crate::{
-    etc::is_mutated,
-    genome::{Genome, TypeSynthesis},
+    config::{CellConfig, SimConfig},
+    etc::{SimRng, is_mutated},
+    genome::{Gene, Genome, TypeSynthesis},
     math::{Direction, Position},
+    mutation::{MutationProfile, perturb_rate},
     traits::Mutable,
-    world::{HEIGHT, World},
+    world::World,
 };
 
 pub type Family = u8;
+pub type CellId = u64;
 
-#[derive(Debug, Clone, Copy)]
+pub const NO_PARENT: CellId = 0;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Cell {
+    /// Assigned by `World`, `0` until the cell is spawned
+    pub id: CellId,
+    pub parent: CellId,
+    /// Id of the mutant that founded the lineage of the cell
+    pub clade: CellId,
+    pub birth_tick: u64,
     pub family: Family,
     pub fixed: bool,
     pub lifetime: u32,
@@ -23,40 +34,53 @@ pub struct Cell {
     pub toxin: f32,
     pub color: (u8, u8, u8),
     pub genome: Genome,
+    pub mutation: MutationProfile,
+    /// Gene executed by the last update, `None` for a newborn
+    pub last_gene: Option<Gene>,
 }
 
 impl Cell {
-    pub fn new() -> Self {
+    pub fn new(config: &SimConfig, rng: &mut SimRng) -> Self {
         Self {
+            id: NO_PARENT,
+            parent: NO_PARENT,
+            clade: NO_PARENT,
+            birth_tick: 0,
             fixed: false,
-            family: rand::thread_rng().gen_range(0..255u8),
+            family: rng.gen_range(0..255u8),
             lifetime: 0,
-            max_lifetime: 16,
-            health: 1.0,
-            energy: 10.0,
+            max_lifetime: config.cell.initial_max_lifetime,
+            health: config.cell.initial_health,
+            energy: config.cell.initial_energy,
             toxin: 0.0,
             color: (100, 100, 100),
-            genome: Genome::new(),
+            genome: Genome::new(config.count_genes),
+            mutation: config.mutation,
+            last_gene: None,
         }
     }
 
-    fn rand_color() -> (u8, u8, u8) {
+    fn rand_color(rng: &mut SimRng) -> (u8, u8, u8) {
         (
-            rand::thread_rng().gen_range(50..200u8),
-            rand::thread_rng().gen_range(50..200u8),
-            rand::thread_rng().gen_range(50..200u8),
+            rng.gen_range(50..200u8),
+            rng.gen_range(50..200u8),
+            rng.gen_range(50..200u8),
         )
     }
 
-    pub fn reproduction(&mut self) -> Option<Self> {
-        if self.energy > 2.5 {
+    /// `pos` - position of the offspring
+    pub fn reproduction(&mut self, pos: Position, world: &mut World) -> Option<Self> {
+        if self.energy > world.config().cell.reproduction_threshold {
             self.energy /= 2.0;
             self.lifetime = 0;
             self.toxin /= 2.0;
 
-            let mut new_cell = self.clone();
+            let mut new_cell = *self;
             new_cell.genome.step = 0;
-            new_cell.mutate();
+            new_cell.last_gene = None;
+            let profile = new_cell.mutation;
+            let mutated = new_cell.mutate(&profile, world.rng());
+            world.register_birth(self, &mut new_cell, pos, mutated);
 
             return Some(new_cell);
         }
@@ -64,17 +88,17 @@ impl Cell {
         None
     }
 
-    pub fn synthesize(&mut self, type_synthesis: TypeSynthesis) {
+    pub fn synthesize(&mut self, type_synthesis: TypeSynthesis, config: &CellConfig) {
         match type_synthesis {
             TypeSynthesis::Energy => {
-                self.energy += 5.0 / (self.energy * 2.25);
+                self.energy += config.synthesis_energy / (self.energy * config.synthesis_divisor);
             }
             TypeSynthesis::Toxin => {
-                self.energy -= 1.0;
+                self.energy -= config.synthesis_cost;
                 self.toxin += 1.0;
             }
             TypeSynthesis::Health => {
-                self.energy -= 1.0;
+                self.energy -= config.synthesis_cost;
                 self.health += 1.0;
             }
         }
@@ -99,7 +123,7 @@ impl Cell {
             return;
         }
 
-        let k = rand::thread_rng().gen_range(0..2u8);
+        let k = world.rng().gen_range(0..2u8);
 
         match k {
             0 => {
@@ -121,6 +145,8 @@ impl Cell {
     }
 
     pub fn update(&mut self, self_pos: &mut Position, world: &mut World) {
+        let config = *world.config();
+        let (tick, observed) = (world.tick(), world.has_event_observers());
         self.update_gravity(self_pos, world);
 
         let gene = *self.genome.get();
@@ -131,50 +157,57 @@ impl Cell {
                 }
             }
             crate::genome::Gene::MoveEnergy(direction) => {
-                if world.is_valid_pos(*self_pos + direction) {
-                    match world.get_mut(*self_pos + direction) {
-                        Some(cell) => {
-                            if self.family == cell.family {
-                                if self.energy > cell.energy {
-                                    let k = self.energy - cell.energy;
-                                    self.energy -= k;
-                                    cell.energy += k;
-                                } else {
-                                    let k = cell.energy - self.energy;
-                                    self.energy += k;
-                                    cell.energy -= k;
-                                }
+                if world.is_valid_pos(*self_pos + direction)
+                    && let Some(cell) = world.get_mut(*self_pos + direction)
+                    && self.family == cell.family
+                {
+                    let giving = self.energy > cell.energy;
+                    let k;
+                    if giving {
+                        k = self.energy - cell.energy;
+                        self.energy -= k;
+                        cell.energy += k;
+                    } else {
+                        k = cell.energy - self.energy;
+                        self.energy += k;
+                        cell.energy -= k;
+                    }
+                    if observed {
+                        let other = *cell;
+                        world.notify(|observer| {
+                            if giving {
+                                observer.on_energy_transfer(tick, self, &other, k)
+                            } else {
+                                observer.on_energy_transfer(tick, &other, self, k)
                             }
-                        }
-                        None => {}
+                        });
                     }
                 }
             }
             crate::genome::Gene::Reproduction(direction) => {
-                if world.is_valid_pos(*self_pos + direction) {
-                    let new_cell = self.reproduction();
-                    match new_cell {
-                        Some(cell) => {
-                            let new_pos = *self_pos + direction;
-                            world.add(new_pos, cell);
-                        }
-                        None => {}
-                    }
+                let new_pos = *self_pos + direction;
+                if world.is_valid_pos(new_pos)
+                    && let Some(cell) = self.reproduction(new_pos, world)
+                {
+                    world.add(new_pos, cell);
                 }
             }
-            crate::genome::Gene::Synthesis(type_synthesis) => self.synthesize(type_synthesis),
+            crate::genome::Gene::Synthesis(type_synthesis) => {
+                self.synthesize(type_synthesis, &config.cell)
+            }
             crate::genome::Gene::Attack(direction) => {
                 // TODO:
-                match world.get_mut(*self_pos + direction) {
-                    Some(cell) => {
-                        if self.family != cell.family {
-                            let k = 3. * self.health;
-                            self.energy += k;
-                            cell.energy -= k;
-                            cell.health -= k;
-                        }
+                if let Some(cell) = world.get_mut(*self_pos + direction)
+                    && self.family != cell.family
+                {
+                    let k = config.cell.attack_factor * self.health;
+                    self.energy += k;
+                    cell.energy -= k;
+                    cell.health -= k;
+                    if observed {
+                        let victim = *cell;
+                        world.notify(|observer| observer.on_attack(tick, self, &victim, k));
                     }
-                    None => {}
                 }
             }
             crate::genome::Gene::Stop => {
@@ -186,25 +219,47 @@ impl Cell {
             }
         }
         self.genome.next();
-        self.energy -= 0.003 * self.max_lifetime as f32 * self.health * ((HEIGHT - self_pos.y()) as f32 / 2.0);
+        self.energy -= config.cell.metabolism
+            * self.max_lifetime as f32
+            * self.health
+            * ((world.height() - self_pos.y()) as f32 / 2.0);
         self.lifetime += 1;
     }
 
-    pub fn is_alive(&self) -> bool {
-        self.energy >= 1.3
-            && self.energy < 1000.0
-            && self.health > 0.0
+    pub fn is_alive(&self, config: &CellConfig) -> bool {
+        self.death_cause(config).is_none()
+    }
+
+    /// A NaN health or energy is a death
+    pub fn death_cause(&self, config: &CellConfig) -> Option<DeathCause> {
+        if self.health.is_nan() || self.health <= 0.0 {
+            Some(DeathCause::Injury)
+        } else if self.energy.is_nan() || self.energy < config.min_energy {
+            Some(DeathCause::Starvation)
+        } else if self.energy >= config.max_energy {
+            Some(DeathCause::Overflow)
+        } else {
+            None
+        }
     }
 }
 
 impl Mutable for Cell {
-    fn mutate(&mut self) -> bool {
-        if is_mutated(1.0) {
-            self.genome.mutate();
-            self.fixed = rand::thread_rng().gen_bool(crate::consts::PROBABILITY_OF_MUTATION);
-            self.color = Self::rand_color();
-            self.family = rand::thread_rng().gen_range(0..255u8);
-            self.max_lifetime = rand::thread_rng().gen_range(0..1000);
+    fn mutate(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool {
+        if is_mutated(rng, profile.cell) {
+            self.genome.mutate(profile, rng);
+            self.fixed = is_mutated(rng, profile.fixed);
+            if is_mutated(rng, profile.color) {
+                self.color = Self::rand_color(rng);
+            }
+            if is_mutated(rng, profile.family) {
+                self.family = rng.gen_range(0..255u8);
+            }
+            if is_mutated(rng, profile.max_lifetime) {
+                self.max_lifetime = rng.gen_range(0..profile.max_lifetime_limit);
+            }
+            self.mutation.cell = perturb_rate(rng, profile.cell, profile.rate_drift);
+            self.mutation.drift(rng);
 
             return true;
         }
@@ -213,8 +268,102 @@ impl Mutable for Cell {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, VariantCount, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeathCause {
+    /// `health` dropped to zero
+    Injury,
+    /// `energy` below `CellConfig::min_energy`
+    Starvation,
+    /// `energy` reached `CellConfig::max_energy`
+    Overflow,
+    /// Another cell took the position
+    Displaced,
+    /// Deleted with `World::del`
+    Removed,
+}
+
+impl DeathCause {
+    pub const ALL: [Self; Self::VARIANT_COUNT] = [
+        Self::Injury,
+        Self::Starvation,
+        Self::Overflow,
+        Self::Displaced,
+        Self::Removed,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Injury => "injury",
+            Self::Starvation => "starvation",
+            Self::Overflow => "overflow",
+            Self::Displaced => "displaced",
+            Self::Removed => "removed",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum MarkerCell {
     Global,
     Private(Family),
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn death_causes() {
+        let config = SimConfig::default();
+        let cell = Cell::new(&config, &mut SimRng::seed_from_u64(0));
+        assert_eq!(cell.death_cause(&config.cell), None);
+
+        let with = |health: f32, energy: f32| {
+            Cell {
+                health,
+                energy,
+                ..cell
+            }
+            .death_cause(&config.cell)
+        };
+        assert_eq!(with(0.0, 10.0), Some(DeathCause::Injury));
+        assert_eq!(with(1.0, 1.0), Some(DeathCause::Starvation));
+        assert_eq!(
+            with(1.0, config.cell.max_energy),
+            Some(DeathCause::Overflow)
+        );
+        assert_eq!(with(f32::NAN, 10.0), Some(DeathCause::Injury));
+        assert_eq!(with(1.0, f32::NAN), Some(DeathCause::Starvation));
+    }
+
+    #[test]
+    fn mutants_inherit_a_perturbed_rate() {
+        let mut config = SimConfig::default();
+        config.mutation.cell = 1.0;
+        let mut rng = SimRng::seed_from_u64(1);
+        let parent = Cell::new(&config, &mut rng);
+
+        let mut rates = Vec::new();
+        for _ in 0..20 {
+            let mut child = parent;
+            assert!(child.mutate(&parent.mutation, &mut rng));
+            assert!((0.0..=1.0).contains(&child.mutation.cell));
+            rates.push(child.mutation.cell);
+        }
+        assert!(rates.iter().any(|rate| *rate < 1.0), "{:?}", rates);
+
+        config.mutation.rate_drift = 0.0;
+        let parent = Cell::new(&config, &mut rng);
+        let mut child = parent;
+        assert!(child.mutate(&parent.mutation, &mut rng));
+        assert_eq!(child.mutation.cell, 1.0);
+
+        config.mutation.cell = 0.0;
+        let parent = Cell::new(&config, &mut rng);
+        let mut child = parent;
+        assert!(!child.mutate(&parent.mutation, &mut rng));
+    }
+}
@@ -0,0 +1,106 @@
+use std::collections::HashMap;
+
+use crate::{
+    genome::{Genome, GenotypeHash},
+    world::World,
+};
+
+#[derive(Debug, Clone)]
+pub struct GenotypeRecord {
+    pub hash: GenotypeHash,
+    pub count: usize,
+    /// `None` if the genotype appeared before the world started tracking it
+    pub first_seen: Option<u64>,
+    /// Genome of one of the carriers
+    pub genome: Genome,
+}
+
+impl World {
+    /// `k` most common genotypes of the living cells, the most common first
+    pub fn census(&self, k: usize) -> Vec<GenotypeRecord> {
+        let mut genotypes: HashMap<GenotypeHash, GenotypeRecord> = HashMap::new();
+
+        for (_, cell) in self.iter() {
+            let hash = cell.genome.genotype_hash();
+            genotypes
+                .entry(hash)
+                .or_insert_with(|| GenotypeRecord {
+                    hash,
+                    count: 0,
+                    first_seen: self.genotype_first_seen(hash),
+                    genome: cell.genome,
+                })
+                .count += 1;
+        }
+
+        let mut records: Vec<GenotypeRecord> = genotypes.into_values().collect();
+        records.sort_unstable_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then(a.first_seen.cmp(&b.first_seen))
+                .then(a.hash.cmp(&b.hash))
+        });
+        records.truncate(k);
+
+        records
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{genome::Gene, math::Position, testing};
+
+    #[test]
+    fn genotypes_are_dated_by_their_first_carrier() {
+        let mut config = testing::config(6);
+        config.mutation.cell = 0.0;
+        let mut world = World::new(config);
+        for x in [10, 30] {
+            let cell = world.new_cell();
+            world.spawn(Position::new(x, 20), cell);
+        }
+        for _ in 0..3 {
+            world.update();
+        }
+
+        let mut cell = world.new_cell();
+        cell.genome.set(0, Gene::Stop);
+        let late = cell.genome.genotype_hash();
+        world.spawn(Position::new(50, 20), cell);
+        world.update();
+
+        let census = world.census(10);
+        assert_eq!(census.len(), 2);
+        assert_eq!(census[0].first_seen, Some(0));
+        assert!(census[0].count > 1, "the founders reproduced");
+        assert_eq!(census[1].hash, late);
+        assert_eq!(census[1].first_seen, Some(3));
+        assert_eq!(census[1].count, 1);
+        assert_eq!(world.census(1).len(), 1);
+
+        // the readers of the view see the same history
+        let view = world.view();
+        let seen: Vec<_> = view.census(10).iter().map(|g| g.first_seen).collect();
+        assert_eq!(seen, [Some(0), Some(3)]);
+    }
+
+    #[test]
+    fn equal_counts_are_ordered_by_age() {
+        let mut world = World::new(testing::config(7));
+        let mut hashes = Vec::new();
+        for (tick, gene) in [Gene::Stop, Gene::None].into_iter().enumerate() {
+            let mut cell = world.new_cell();
+            cell.genome.set(1, gene);
+            hashes.push(cell.genome.genotype_hash());
+            world.spawn(Position::new(10 + 20 * tick as i32, 20), cell);
+            // the cells reach no reproduction gene in two ticks
+            world.update();
+        }
+
+        let census = world.census(10);
+        assert_eq!(census.iter().map(|g| g.hash).collect::<Vec<_>>(), hashes);
+        assert_eq!(census[0].first_seen, Some(0));
+        assert_eq!(census[1].first_seen, Some(1));
+    }
+}
@@ -0,0 +1,347 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    observer::Observer,
+    world::{World, WorldSnapshot},
+};
+
+const MAGIC: &[u8; 8] = b"EVOCKPT4";
+const PREFIX: &str = "checkpoint-";
+const EXTENSION: &str = ".bin";
+/// Suffix of a checkpoint being written, see `write_checkpoint`
+const TMP: &str = ".tmp";
+/// Suffix of a checkpoint that could not be read, see `load_latest`
+const BROKEN: &str = ".broken";
+/// Broken checkpoints kept for an inspection, the older ones are removed
+const KEEP_BROKEN: usize = 3;
+
+/// Writes checkpoints of a `World` into a directory, keeping the last `keep`.
+///
+/// As an observer it saves after the updates when a checkpoint is due,
+/// the errors are reported to stderr and do not stop the simulation.
+#[derive(Debug)]
+pub struct Checkpointer {
+    dir: PathBuf,
+    interval_ticks: Option<u64>,
+    interval: Option<Duration>,
+    keep: usize,
+    last_save: Instant,
+}
+
+impl Checkpointer {
+    /// Creates `dir` if needed, nothing is saved until an interval is set.
+    /// The files left by the interrupted writes are removed
+    pub fn new<P: Into<PathBuf>>(dir: P) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        remove_unfinished(&dir)?;
+        Ok(Self {
+            dir,
+            interval_ticks: None,
+            interval: None,
+            keep: 3,
+            last_save: Instant::now(),
+        })
+    }
+
+    pub fn with_interval_ticks(mut self, ticks: u64) -> Self {
+        self.interval_ticks = Some(ticks.max(1));
+        self
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    #[inline(always)]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn is_due(&self, world: &World) -> bool {
+        self.interval_ticks
+            .is_some_and(|ticks| world.tick().is_multiple_of(ticks))
+            || self
+                .interval
+                .is_some_and(|interval| self.last_save.elapsed() >= interval)
+    }
+
+    /// Writes a checkpoint and removes the oldest ones
+    pub fn save(&mut self, world: &World) -> io::Result<PathBuf> {
+        self.last_save = Instant::now();
+        let path = self
+            .dir
+            .join(format!("{}{:012}{}", PREFIX, world.tick(), EXTENSION));
+        write_checkpoint(&path, world)?;
+
+        let checkpoints = list_checkpoints(&self.dir)?;
+        let excess = checkpoints.len().saturating_sub(self.keep);
+        for (_, old) in &checkpoints[..excess] {
+            fs::remove_file(old)?;
+        }
+
+        Ok(path)
+    }
+}
+
+impl Observer for Checkpointer {
+    fn on_tick(&mut self, world: &World) {
+        if self.is_due(world)
+            && let Err(e) = self.save(world)
+        {
+            eprintln!("failed to write checkpoint: {}", e);
+        }
+    }
+
+    fn wants_events(&self) -> bool {
+        false
+    }
+}
+
+/// Writes through a temporary file, so `path` holds either the previous
+/// content or the complete checkpoint
+pub fn write_checkpoint<P: AsRef<Path>>(path: P, world: &World) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(TMP);
+
+    let mut writer = BufWriter::new(File::create(&tmp)?);
+    writer.write_all(MAGIC)?;
+    world.snapshot().write_to(&mut writer)?;
+    writer.into_inner()?.sync_all()?;
+    fs::rename(&tmp, path)?;
+
+    // makes the rename durable, not supported on every platform
+    if let Some(dir) = path.parent()
+        && let Ok(dir) = File::open(dir)
+    {
+        let _ = dir.sync_all();
+    }
+
+    Ok(())
+}
+
+pub fn read_checkpoint<P: AsRef<Path>>(path: P) -> io::Result<World> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; MAGIC.len()];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid_data("not a checkpoint"));
+    }
+
+    let snapshot = WorldSnapshot::read_from(&mut reader)?;
+    if reader.read(&mut [0])? != 0 {
+        return Err(invalid_data("trailing data after the checkpoint"));
+    }
+
+    World::from_snapshot(snapshot)
+}
+
+/// Checkpoints of `dir` sorted by tick, empty if `dir` does not exist
+pub fn list_checkpoints<P: AsRef<Path>>(dir: P) -> io::Result<Vec<(u64, PathBuf)>> {
+    list_ticks(dir.as_ref(), EXTENSION)
+}
+
+/// Files `<PREFIX><tick><suffix>` of `dir` sorted by tick
+fn list_ticks(dir: &Path, suffix: &str) -> io::Result<Vec<(u64, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+
+    let mut checkpoints = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let tick = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(PREFIX)?.strip_suffix(suffix))
+            .and_then(|tick| tick.parse::<u64>().ok());
+        if let Some(tick) = tick {
+            checkpoints.push((tick, path));
+        }
+    }
+    checkpoints.sort_unstable();
+
+    Ok(checkpoints)
+}
+
+/// The newest checkpoint of `dir` that can be read.
+///
+/// Newer broken checkpoints are renamed to `*.broken`, otherwise
+/// the rotation would keep them instead of the valid ones. Only the last
+/// `KEEP_BROKEN` of them are kept. The files left by the interrupted writes
+/// are removed.
+pub fn load_latest<P: AsRef<Path>>(dir: P) -> io::Result<Option<(PathBuf, World)>> {
+    let dir = dir.as_ref();
+    remove_unfinished(dir)?;
+    let mut latest = None;
+    for (_, path) in list_checkpoints(dir)?.into_iter().rev() {
+        match read_checkpoint(&path) {
+            Ok(world) => {
+                latest = Some((path, world));
+                break;
+            }
+            Err(e) => {
+                eprintln!("{}: skipped: {}", path.display(), e);
+                let mut broken = path.as_os_str().to_owned();
+                broken.push(BROKEN);
+                fs::rename(&path, broken)?;
+            }
+        }
+    }
+
+    let broken = list_ticks(dir, &format!("{}{}", EXTENSION, BROKEN))?;
+    for (_, path) in broken.iter().rev().skip(KEEP_BROKEN) {
+        fs::remove_file(path)?;
+    }
+
+    Ok(latest)
+}
+
+/// Removes the checkpoints of `dir` whose write was interrupted
+fn remove_unfinished(dir: &Path) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+
+    for entry in entries {
+        let path = entry?.path();
+        let unfinished = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_prefix(PREFIX)?.strip_suffix(TMP))
+            .is_some_and(|name| name.ends_with(EXTENSION));
+        if unfinished {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+#[inline(always)]
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, TempDir, state};
+
+    fn world() -> World {
+        testing::populated(testing::config(9), 8)
+    }
+
+    fn ticks(dir: &Path) -> Vec<u64> {
+        list_checkpoints(dir)
+            .unwrap()
+            .into_iter()
+            .map(|(tick, _)| tick)
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("round-trip");
+        let mut world = world();
+        world.update();
+        let path = Checkpointer::new(&dir.0).unwrap().save(&world).unwrap();
+        assert_eq!(state(&read_checkpoint(path).unwrap()), state(&world));
+    }
+
+    #[test]
+    fn oldest_checkpoints_are_removed() {
+        let dir = TempDir::new("rotation");
+        let checkpointer = Checkpointer::new(&dir.0)
+            .unwrap()
+            .with_interval_ticks(2)
+            .with_keep(2);
+        let mut world = world();
+        world.add_observer(Box::new(checkpointer));
+        for _ in 0..7 {
+            world.update();
+        }
+        assert_eq!(ticks(&dir.0), [4, 6]);
+
+        let mut checkpointer = Checkpointer::new(&dir.0).unwrap().with_keep(1);
+        checkpointer.save(&world).unwrap();
+        assert_eq!(ticks(&dir.0), [7]);
+    }
+
+    #[test]
+    fn broken_checkpoints_are_renamed() {
+        let dir = TempDir::new("broken");
+        let world = world();
+        let valid = Checkpointer::new(&dir.0).unwrap().save(&world).unwrap();
+        let broken = dir.0.join(format!("{}{:012}{}", PREFIX, 9, EXTENSION));
+        fs::write(&broken, [&MAGIC[..], b" cut by a crash"].concat()).unwrap();
+
+        let (path, restored) = load_latest(&dir.0).unwrap().unwrap();
+        assert_eq!(path, valid);
+        assert_eq!(state(&restored), state(&world));
+        assert!(!broken.exists());
+        assert!(
+            dir.0
+                .join(format!("{}{:012}{}.broken", PREFIX, 9, EXTENSION))
+                .exists()
+        );
+        assert_eq!(ticks(&dir.0), [0]);
+    }
+
+    #[test]
+    fn only_the_last_broken_checkpoints_are_kept() {
+        let dir = TempDir::new("broken-kept");
+        fs::create_dir_all(&dir.0).unwrap();
+        let broken = |tick: u64| dir.0.join(format!("{}{:012}{}", PREFIX, tick, EXTENSION));
+        for tick in 1..=KEEP_BROKEN as u64 + 2 {
+            fs::write(broken(tick), b"cut by a crash").unwrap();
+        }
+
+        assert!(load_latest(&dir.0).unwrap().is_none());
+        let kept: Vec<u64> = list_ticks(&dir.0, &format!("{}{}", EXTENSION, BROKEN))
+            .unwrap()
+            .into_iter()
+            .map(|(tick, _)| tick)
+            .collect();
+        assert_eq!(kept, [3, 4, 5]);
+        assert!(ticks(&dir.0).is_empty());
+    }
+
+    #[test]
+    fn unfinished_writes_are_removed() {
+        let dir = TempDir::new("unfinished");
+        fs::create_dir_all(&dir.0).unwrap();
+        let unfinished = dir
+            .0
+            .join(format!("{}{:012}{}{}", PREFIX, 3, EXTENSION, TMP));
+        let other = dir.0.join("notes.tmp");
+        fs::write(&unfinished, MAGIC).unwrap();
+        fs::write(&other, b"").unwrap();
+
+        assert!(load_latest(&dir.0).unwrap().is_none());
+        assert!(!unfinished.exists());
+        assert!(other.exists());
+    }
+
+    #[test]
+    fn missing_dir_has_no_checkpoint() {
+        let dir = TempDir::new("missing");
+        assert!(list_checkpoints(&dir.0).unwrap().is_empty());
+        assert!(load_latest(&dir.0).unwrap().is_none());
+    }
+}
@@ -0,0 +1,330 @@
+use std::fmt;
+
+use crate::{cell::Cell, genome::Genome, math::Position, pos, world::World};
+
+pub const MAX_RADIUS: i32 = 32;
+
+/// What a click or a drag of the mouse does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tool {
+    /// Selects the cell under the cursor
+    #[default]
+    Inspect,
+    /// Spawns cells with `Brush::genome`
+    Paint,
+    /// Removes cells and walls
+    Erase,
+    Wall,
+    /// Gives `Brush::energy` to every cell under the brush
+    Energy,
+    /// Copies the dragged rectangle
+    Copy,
+    Paste,
+}
+
+impl Tool {
+    /// Applied on every position of the drag
+    #[inline]
+    pub fn is_stroke(&self) -> bool {
+        matches!(self, Tool::Paint | Tool::Erase | Tool::Wall | Tool::Energy)
+    }
+}
+
+impl fmt::Display for Tool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Tool::Inspect => "inspect",
+            Tool::Paint => "paint",
+            Tool::Erase => "erase",
+            Tool::Wall => "wall",
+            Tool::Energy => "energy",
+            Tool::Copy => "copy",
+            Tool::Paste => "paste",
+        })
+    }
+}
+
+/// Cells and walls of a rectangle, positions are relative to its top left corner
+#[derive(Debug, Clone, Default)]
+pub struct Clipboard {
+    width: i32,
+    height: i32,
+    cells: Vec<(Position, Cell)>,
+    walls: Vec<Position>,
+}
+
+impl Clipboard {
+    /// `a` and `b` are opposite corners, both included
+    pub fn copy(world: &World, a: Position, b: Position) -> Self {
+        let min = pos!(a.x().min(b.x()), a.y().min(b.y()));
+        let max = pos!(a.x().max(b.x()), a.y().max(b.y()));
+
+        let mut clipboard = Self {
+            width: max.x() - min.x() + 1,
+            height: max.y() - min.y() + 1,
+            ..Default::default()
+        };
+        for y in min.y()..=max.y() {
+            for x in min.x()..=max.x() {
+                let offset = pos!(x - min.x(), y - min.y());
+                if let Some(cell) = world.cell(pos!(x, y)) {
+                    clipboard.cells.push((offset, *cell));
+                }
+                if world.is_wall(pos!(x, y)) {
+                    clipboard.walls.push(offset);
+                }
+            }
+        }
+
+        clipboard
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> (i32, i32) {
+        (self.width, self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty() && self.walls.is_empty()
+    }
+
+    /// Replaces the region at `at`, the pasted cells are spawned with new ids
+    pub fn paste(&self, world: &mut World, at: Position) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let pos = at + (x, y);
+                world.del(pos);
+                world.set_wall(pos, false);
+            }
+        }
+        for offset in &self.walls {
+            world.set_wall(at + (offset.x(), offset.y()), true);
+        }
+        for (offset, cell) in &self.cells {
+            world.spawn(at + (offset.x(), offset.y()), *cell);
+        }
+    }
+}
+
+/// Settings of the editing tools
+#[derive(Debug, Clone)]
+pub struct Brush {
+    pub tool: Tool,
+    pub radius: i32,
+    /// Energy given by `Tool::Energy` on every application
+    pub energy: f32,
+    /// Genome of the painted cells, the initial one if not set
+    pub genome: Option<Genome>,
+    pub clipboard: Clipboard,
+}
+
+impl Brush {
+    pub fn new() -> Self {
+        Self {
+            tool: Tool::default(),
+            radius: 2,
+            energy: 5.0,
+            genome: None,
+            clipboard: Clipboard::default(),
+        }
+    }
+
+    /// Settings of the stroke tools, cheap to move to another thread
+    pub fn without_clipboard(&self) -> Self {
+        Self {
+            clipboard: Clipboard::default(),
+            ..*self
+        }
+    }
+
+    pub fn grow(&mut self) {
+        self.radius = (self.radius + 1).min(MAX_RADIUS);
+    }
+
+    pub fn shrink(&mut self) {
+        self.radius = (self.radius - 1).max(0);
+    }
+
+    /// Positions of the disc under the brush
+    pub fn area(&self, center: Position) -> impl Iterator<Item = Position> + use<> {
+        let radius = self.radius;
+        (-radius..=radius)
+            .flat_map(move |dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(move |(dx, dy)| dx * dx + dy * dy <= radius * radius)
+            .map(move |d| center + d)
+    }
+
+    /// Applies a stroke tool at `center`, the other tools do nothing
+    pub fn apply(&self, world: &mut World, center: Position) {
+        for pos in self.area(center) {
+            match self.tool {
+                Tool::Paint => {
+                    if world.cell(pos).is_none() && world.is_valid_pos(pos) {
+                        let mut cell = world.new_cell();
+                        if let Some(genome) = self.genome {
+                            cell.genome = genome;
+                            cell.genome.step = 0;
+                        }
+                        world.spawn(pos, cell);
+                    }
+                }
+                Tool::Erase => {
+                    world.del(pos);
+                    world.set_wall(pos, false);
+                }
+                Tool::Wall => {
+                    world.set_wall(pos, true);
+                }
+                Tool::Energy => {
+                    if let Some(cell) = world.cell_mut(pos) {
+                        cell.energy += self.energy;
+                    }
+                }
+                Tool::Inspect | Tool::Copy | Tool::Paste => return,
+            }
+        }
+    }
+
+    /// Applies the brush along the segment, so a fast drag leaves no gaps
+    pub fn stroke(&self, world: &mut World, from: Position, to: Position) {
+        let (dx, dy) = (to.x() - from.x(), to.y() - from.y());
+        let steps = dx.abs().max(dy.abs()).max(1);
+        for i in 0..=steps {
+            let pos = pos!(from.x() + dx * i / steps, from.y() + dy * i / steps);
+            self.apply(world, pos);
+        }
+    }
+}
+
+impl Default for Brush {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{genome::Gene, testing};
+
+    fn brush(tool: Tool, radius: i32) -> Brush {
+        Brush {
+            tool,
+            radius,
+            ..Brush::new()
+        }
+    }
+
+    fn world() -> World {
+        World::new(testing::config(16))
+    }
+
+    #[test]
+    fn area_is_a_disc_around_the_centre() {
+        let count = |radius| brush(Tool::Paint, radius).area(pos!(0, 0)).count();
+        assert_eq!([count(0), count(1), count(2)], [1, 5, 13]);
+        let area: Vec<Position> = brush(Tool::Paint, 1).area(pos!(10, 20)).collect();
+        assert_eq!(
+            area,
+            [
+                pos!(10, 19),
+                pos!(9, 20),
+                pos!(10, 20),
+                pos!(11, 20),
+                pos!(10, 21)
+            ]
+        );
+
+        let mut brush = brush(Tool::Paint, MAX_RADIUS);
+        brush.grow();
+        assert_eq!(brush.radius, MAX_RADIUS);
+        brush.radius = 0;
+        brush.shrink();
+        assert_eq!(brush.radius, 0);
+    }
+
+    #[test]
+    fn paint_erase_and_walls() {
+        let mut world = world();
+        let mut paint = brush(Tool::Paint, 1);
+        let mut genome = Genome::new(4);
+        genome.set(0, Gene::Stop);
+        genome.step = 3;
+        paint.genome = Some(genome);
+        paint.apply(&mut world, pos!(10, 10));
+        assert_eq!(world.living().count(), 5);
+        let cell = world.cell(pos!(11, 10)).unwrap();
+        assert_eq!(cell.genome, genome);
+        assert_eq!(cell.genome.step, 0);
+
+        // painting again does not replace the cells
+        let id = cell.id;
+        paint.apply(&mut world, pos!(10, 10));
+        assert_eq!(world.cell(pos!(11, 10)).unwrap().id, id);
+
+        brush(Tool::Wall, 0).apply(&mut world, pos!(20, 10));
+        assert!(world.is_wall(pos!(20, 10)));
+        // no cell is painted on a wall
+        brush(Tool::Paint, 0).apply(&mut world, pos!(20, 10));
+        assert!(world.cell(pos!(20, 10)).is_none());
+
+        brush(Tool::Erase, 0).apply(&mut world, pos!(10, 10));
+        assert_eq!(world.living().count(), 4);
+        brush(Tool::Erase, 0).apply(&mut world, pos!(20, 10));
+        assert!(!world.is_wall(pos!(20, 10)));
+    }
+
+    #[test]
+    fn energy_is_added_to_the_cells() {
+        let mut world = world();
+        brush(Tool::Paint, 0).apply(&mut world, pos!(10, 10));
+        let before = world.cell(pos!(10, 10)).unwrap().energy;
+        let energy = Brush {
+            energy: 2.5,
+            ..brush(Tool::Energy, 3)
+        };
+        energy.apply(&mut world, pos!(11, 11));
+        assert_eq!(world.cell(pos!(10, 10)).unwrap().energy, before + 2.5);
+        assert_eq!(world.living().count(), 1);
+
+        // the other tools do not edit
+        brush(Tool::Inspect, 3).apply(&mut world, pos!(10, 10));
+        brush(Tool::Copy, 3).apply(&mut world, pos!(10, 10));
+        assert_eq!(world.living().count(), 1);
+    }
+
+    #[test]
+    fn stroke_leaves_no_gaps() {
+        let mut world = world();
+        brush(Tool::Wall, 0).stroke(&mut world, pos!(5, 5), pos!(12, 8));
+        let walls: Vec<Position> = world.walls().collect();
+        assert_eq!(walls.len(), 8);
+        for pair in walls.windows(2) {
+            assert!((pair[1].x() - pair[0].x()).abs() <= 1);
+            assert!((pair[1].y() - pair[0].y()).abs() <= 1);
+        }
+        assert!(world.is_wall(pos!(5, 5)) && world.is_wall(pos!(12, 8)));
+    }
+
+    #[test]
+    fn paste_is_relative_to_the_corner() {
+        let mut world = world();
+        brush(Tool::Paint, 0).apply(&mut world, pos!(10, 10));
+        brush(Tool::Wall, 0).apply(&mut world, pos!(11, 12));
+        // the corners in any order
+        let clipboard = Clipboard::copy(&world, pos!(11, 12), pos!(10, 10));
+        assert_eq!(clipboard.size(), (2, 3));
+        assert!(!clipboard.is_empty());
+
+        brush(Tool::Paint, 0).apply(&mut world, pos!(31, 21));
+        clipboard.paste(&mut world, pos!(30, 20));
+        let copy = world.cell(pos!(30, 20)).unwrap();
+        assert_ne!(copy.id, world.cell(pos!(10, 10)).unwrap().id);
+        assert!(world.is_wall(pos!(31, 22)));
+        // the region is replaced
+        assert!(world.cell(pos!(31, 21)).is_none());
+        assert_eq!(world.living().count(), 2);
+
+        assert!(Clipboard::copy(&world, pos!(40, 5), pos!(41, 6)).is_empty());
+    }
+}
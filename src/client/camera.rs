@@ -0,0 +1,216 @@
+use crate::{cell::Cell, math::Position, pos, world::World};
+
+/// Pixels per cell
+pub const MIN_ZOOM: f32 = 0.25;
+pub const MAX_ZOOM: f32 = 64.0;
+
+/// Maps the cells of a world to the pixels of a viewport
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    /// Pixels per cell
+    zoom: f32,
+    /// Position of the world origin in the viewport, in pixels
+    offset: (f32, f32),
+    viewport: (u32, u32),
+}
+
+impl Camera {
+    pub fn new(viewport: (u32, u32), zoom: f32) -> Self {
+        Self {
+            zoom: zoom.clamp(MIN_ZOOM, MAX_ZOOM),
+            offset: (0.0, 0.0),
+            viewport,
+        }
+    }
+
+    #[inline(always)]
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    #[inline(always)]
+    pub fn viewport(&self) -> (u32, u32) {
+        self.viewport
+    }
+
+    /// Keeps the centre of the view in place
+    pub fn set_viewport(&mut self, width: u32, height: u32) {
+        self.offset.0 += (width as f32 - self.viewport.0 as f32) / 2.0;
+        self.offset.1 += (height as f32 - self.viewport.1 as f32) / 2.0;
+        self.viewport = (width, height);
+    }
+
+    /// Top left corner of the cell in the viewport
+    #[inline]
+    pub fn to_screen(&self, pos: Position) -> (f32, f32) {
+        (
+            self.offset.0 + pos.x() as f32 * self.zoom,
+            self.offset.1 + pos.y() as f32 * self.zoom,
+        )
+    }
+
+    /// Cell under the pixel
+    #[inline]
+    pub fn to_world(&self, x: f32, y: f32) -> Position {
+        pos!(
+            ((x - self.offset.0) / self.zoom).floor() as i32,
+            ((y - self.offset.1) / self.zoom).floor() as i32
+        )
+    }
+
+    /// Zooms by `factor`, the point under the pixel `(x, y)` stays in place
+    pub fn zoom_at(&mut self, factor: f32, x: f32, y: f32) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        let k = zoom / self.zoom;
+        self.offset.0 = x - (x - self.offset.0) * k;
+        self.offset.1 = y - (y - self.offset.1) * k;
+        self.zoom = zoom;
+    }
+
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        self.offset.0 += dx;
+        self.offset.1 += dy;
+    }
+
+    /// Shows the whole world in the centre of the viewport
+    pub fn fit(&mut self, width: i32, height: i32) {
+        let (view_width, view_height) = (self.viewport.0 as f32, self.viewport.1 as f32);
+        let (width, height) = (width.max(1) as f32, height.max(1) as f32);
+        self.zoom = (view_width / width)
+            .min(view_height / height)
+            .clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = (
+            (view_width - width * self.zoom) / 2.0,
+            (view_height - height * self.zoom) / 2.0,
+        );
+    }
+
+    /// Visible cells of a `width` x `height` world: the top left
+    /// (inclusive) and the bottom right (exclusive) corners
+    pub fn visible(&self, width: i32, height: i32) -> (Position, Position) {
+        let min = self.to_world(0.0, 0.0);
+        let max = self.to_world(self.viewport.0 as f32, self.viewport.1 as f32);
+        (
+            pos!(min.x().clamp(0, width), min.y().clamp(0, height)),
+            pos!(
+                (max.x() + 1).clamp(0, width),
+                (max.y() + 1).clamp(0, height)
+            ),
+        )
+    }
+
+    /// Calls `f` for the visible cells of `world` only
+    pub fn for_each_visible<F: FnMut(Position, &Cell)>(&self, world: &World, mut f: F) {
+        let (min, max) = self.visible(world.width(), world.height());
+        let area = (max.x() - min.x()) as usize * (max.y() - min.y()) as usize;
+
+        // zoomed in, looking up the visible positions is cheaper than iterating all cells
+        if area < world.count_cells() {
+            for y in min.y()..max.y() {
+                for x in min.x()..max.x() {
+                    if let Some(cell) = world.get(pos!(x, y)) {
+                        f(pos!(x, y), cell);
+                    }
+                }
+            }
+        } else {
+            world
+                .iter()
+                .filter(|(pos, _)| {
+                    (min.x()..max.x()).contains(&pos.x()) && (min.y()..max.y()).contains(&pos.y())
+                })
+                .for_each(|(pos, cell)| f(*pos, cell));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn screen_and_world_are_inverse() {
+        let mut camera = Camera::new((200, 100), 4.0);
+        camera.pan(10.0, -6.0);
+        let pos = pos!(7, 3);
+        assert_eq!(camera.to_screen(pos), (38.0, 6.0));
+        let (x, y) = camera.to_screen(pos);
+        // every pixel of the cell maps back to it
+        assert_eq!(camera.to_world(x, y), pos);
+        assert_eq!(camera.to_world(x + 3.9, y + 3.9), pos);
+        assert_eq!(camera.to_world(x - 0.1, y), pos!(6, 3));
+        // left of the origin rounds down
+        assert_eq!(camera.to_world(9.0, 0.0), pos!(-1, 1));
+    }
+
+    #[test]
+    fn zoom_keeps_the_point_under_the_cursor() {
+        let mut camera = Camera::new((200, 100), 2.0);
+        let (x, y) = (50.0, 30.0);
+        let before = camera.to_world(x, y);
+        camera.zoom_at(4.0, x, y);
+        assert_eq!(camera.zoom(), 8.0);
+        assert_eq!(camera.to_world(x, y), before);
+
+        camera.zoom_at(1000.0, x, y);
+        assert_eq!(camera.zoom(), MAX_ZOOM);
+        camera.zoom_at(0.0, x, y);
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+    }
+
+    #[test]
+    fn fit_centres_the_world() {
+        let mut camera = Camera::new((300, 100), 1.0);
+        camera.fit(50, 25);
+        assert_eq!(camera.zoom(), 4.0);
+        // 200 x 100 pixels in the middle of 300 x 100
+        assert_eq!(camera.to_screen(pos!(0, 0)), (50.0, 0.0));
+        assert_eq!(camera.visible(50, 25), (pos!(0, 0), pos!(50, 25)));
+
+        let centre = camera.to_world(150.0, 50.0);
+        camera.set_viewport(400, 200);
+        assert_eq!(camera.to_world(200.0, 100.0), centre);
+    }
+
+    #[test]
+    fn visible_cells_are_clamped_to_the_world() {
+        let mut camera = Camera::new((40, 20), 10.0);
+        camera.pan(-25.0, 15.0);
+        // pixel (0, 0) is the cell (2, -2), (40, 20) is the cell (6, 0)
+        assert_eq!(camera.visible(100, 100), (pos!(2, 0), pos!(7, 1)));
+        assert_eq!(camera.visible(5, 100), (pos!(2, 0), pos!(5, 1)));
+    }
+
+    #[test]
+    fn culling_finds_the_same_cells_either_way() {
+        let mut world = testing::populated(testing::config(14), 3);
+        world.update();
+        let visible = |camera: &Camera| {
+            let mut cells = Vec::new();
+            camera.for_each_visible(&world, |pos, cell| cells.push((pos, cell.id)));
+            cells.sort_unstable();
+            cells
+        };
+
+        // zoomed in, the positions are looked up
+        let mut camera = Camera::new((60, 60), 6.0);
+        camera.pan(-60.0, -60.0);
+        let (min, max) = camera.visible(world.width(), world.height());
+        let mut expected: Vec<_> = world
+            .iter()
+            .filter(|(pos, _)| {
+                (min.x()..max.x()).contains(&pos.x()) && (min.y()..max.y()).contains(&pos.y())
+            })
+            .map(|(pos, cell)| (*pos, cell.id))
+            .collect();
+        expected.sort_unstable();
+        assert!(!expected.is_empty());
+        assert_eq!(visible(&camera), expected);
+
+        // zoomed out, every cell is visible
+        let mut camera = Camera::new((60, 60), 1.0);
+        camera.fit(world.width(), world.height());
+        assert_eq!(visible(&camera).len(), world.count_cells());
+    }
+}
@@ -0,0 +1,397 @@
+use std::{
+    fmt, thread,
+    time::{Duration, Instant},
+};
+
+pub const FRAMES_PER_SECOND: u32 = 60;
+pub const FRAME_TIME: Duration = Duration::from_nanos(1_000_000_000 / FRAMES_PER_SECOND as u64);
+
+/// Ticks per second at speed 1, see `SimClock::with_tps`
+pub const BASE_TPS: f64 = 60.0;
+/// Lower bound of the ticks per second at speed 1
+pub const MIN_TPS: f64 = 0.01;
+/// Multipliers of `BASE_TPS`
+pub const MIN_SPEED: f64 = 1.0 / 16.0;
+pub const MAX_SPEED: f64 = 1024.0;
+
+/// Simulated time kept when the updates are slower than the target, the rest is dropped
+pub const MAX_CATCH_UP: Duration = Duration::from_millis(250);
+
+/// Fixed-timestep scheduler of the simulation thread and of the headless run.
+///
+/// The world is updated `speed * tps` times per second whatever the
+/// frame rate, and a frame is rendered every `FRAME_TIME`. When the updates
+/// fall behind, the frames are skipped rather than delayed by more than a
+/// frame, and at most `max_catch_up` of the simulated time is caught up
+/// later. In turbo mode the world is updated as fast as possible between
+/// the frames.
+#[derive(Debug)]
+pub struct SimClock {
+    paused: bool,
+    turbo: bool,
+    speed: f64,
+    /// Ticks per second at speed 1
+    base_tps: f64,
+    /// Single steps requested while paused
+    steps: u64,
+    /// Ticks owed at the last call of `due`
+    ticks_due: f64,
+    last_update: Instant,
+    next_frame: Instant,
+    max_catch_up: Duration,
+}
+
+impl SimClock {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        Self {
+            paused: false,
+            turbo: false,
+            speed: 1.0,
+            base_tps: BASE_TPS,
+            steps: 0,
+            ticks_due: 0.0,
+            last_update: now,
+            next_frame: now,
+            max_catch_up: MAX_CATCH_UP,
+        }
+    }
+
+    /// Ticks per second at speed 1, `BASE_TPS` by default
+    pub fn with_tps(mut self, tps: f64) -> Self {
+        self.base_tps = tps.max(MIN_TPS);
+        self
+    }
+
+    /// `MAX_CATCH_UP` by default
+    pub fn with_max_catch_up(mut self, max_catch_up: Duration) -> Self {
+        self.max_catch_up = max_catch_up;
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+        self.ticks_due = 0.0;
+        self.last_update = Instant::now();
+    }
+
+    /// Pauses and runs `ticks` updates
+    pub fn step(&mut self, ticks: u64) {
+        self.paused = true;
+        self.steps += ticks;
+    }
+
+    #[inline(always)]
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Leaves turbo mode
+    pub fn set_speed(&mut self, speed: f64) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        self.turbo = false;
+    }
+
+    pub fn faster(&mut self) {
+        self.set_speed(self.speed * 2.0);
+    }
+
+    pub fn slower(&mut self) {
+        self.set_speed(self.speed / 2.0);
+    }
+
+    #[inline(always)]
+    pub fn is_turbo(&self) -> bool {
+        self.turbo
+    }
+
+    pub fn toggle_turbo(&mut self) {
+        self.turbo = !self.turbo;
+        self.ticks_due = 0.0;
+    }
+
+    /// Target ticks per second
+    #[inline(always)]
+    pub fn tps(&self) -> f64 {
+        self.speed * self.base_tps
+    }
+
+    /// Updates owed since the last call, unbounded in turbo mode.
+    ///
+    /// Report the number actually run with `ran`.
+    pub fn due(&mut self) -> u64 {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        self.last_update = now;
+
+        if self.paused {
+            return self.steps;
+        }
+        if self.turbo {
+            return u64::MAX;
+        }
+
+        let max_due = (self.tps() * self.max_catch_up.as_secs_f64()).max(1.0);
+        self.ticks_due = (self.ticks_due + elapsed * self.tps()).min(max_due);
+        self.steps + self.ticks_due as u64
+    }
+
+    /// `ticks` of the updates returned by `due` were run
+    pub fn ran(&mut self, ticks: u64) {
+        let steps = ticks.min(self.steps);
+        self.steps -= steps;
+        if !self.paused {
+            self.ticks_due = (self.ticks_due - (ticks - steps) as f64).max(0.0);
+        }
+    }
+
+    /// Calls `update` for the updates due, until a frame must be rendered.
+    /// Returns the number of calls
+    pub fn run_due<F: FnMut()>(&mut self, mut update: F) -> u64 {
+        let due = self.due();
+        let mut ran = 0;
+        while ran < due {
+            update();
+            ran += 1;
+            // a frame is skipped rather than delayed
+            if self.must_yield() {
+                break;
+            }
+        }
+        self.ran(ran);
+        ran
+    }
+
+    /// The updates must stop for a frame: it is due in turbo mode, otherwise
+    /// late by a whole frame
+    pub fn must_yield(&self) -> bool {
+        let deadline = if self.turbo && !self.paused {
+            self.next_frame
+        } else {
+            self.next_frame + FRAME_TIME
+        };
+        Instant::now() >= deadline
+    }
+
+    #[inline]
+    pub fn is_frame_due(&self) -> bool {
+        Instant::now() >= self.next_frame
+    }
+
+    /// Schedules the next frame, the missed ones are skipped
+    pub fn frame_rendered(&mut self) {
+        let now = Instant::now();
+        self.next_frame += FRAME_TIME;
+        if self.next_frame < now {
+            self.next_frame = now + FRAME_TIME;
+        }
+    }
+
+    /// Time until the next update or frame is due, `None` if one is due now
+    pub fn wait_time(&self) -> Option<Duration> {
+        if self.steps > 0 || (!self.paused && (self.turbo || self.ticks_due >= 1.0)) {
+            return None;
+        }
+
+        let mut wake = self.next_frame;
+        if !self.paused {
+            let next_tick = (1.0 - self.ticks_due.fract()) / self.tps();
+            wake = wake.min(self.last_update + Duration::from_secs_f64(next_tick));
+        }
+        wake.checked_duration_since(Instant::now())
+    }
+
+    /// Sleeps for `wait_time`
+    pub fn wait(&self) {
+        if let Some(wait) = self.wait_time() {
+            thread::sleep(wait);
+        }
+    }
+
+    pub fn state(&self) -> ClockState {
+        ClockState {
+            paused: self.paused,
+            turbo: self.turbo,
+            speed: self.speed,
+        }
+    }
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for SimClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.state().fmt(f)
+    }
+}
+
+/// Settings of a `SimClock`, shown by the clients
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClockState {
+    pub paused: bool,
+    pub turbo: bool,
+    pub speed: f64,
+}
+
+impl fmt::Display for ClockState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.paused {
+            write!(f, "paused")
+        } else if self.turbo {
+            write!(f, "max")
+        } else {
+            write!(f, "x{}", self.speed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Clock at 10 ticks per second whose last update was `elapsed` ago
+    fn clock(elapsed: Duration) -> SimClock {
+        let mut clock = SimClock::new()
+            .with_tps(10.0)
+            .with_max_catch_up(Duration::from_secs(1));
+        clock.last_update = Instant::now() - elapsed;
+        clock
+    }
+
+    #[test]
+    fn due_follows_the_elapsed_time() {
+        let mut clock = clock(Duration::from_millis(550));
+        assert_eq!(clock.due(), 5);
+        clock.ran(3);
+        // the ticks not run and the fraction stay owed
+        assert_eq!(clock.due(), 2);
+        clock.ran(2);
+        assert!(clock.ticks_due < 1.0);
+    }
+
+    #[test]
+    fn catch_up_is_bounded() {
+        let mut clock = clock(Duration::from_secs(30));
+        assert_eq!(clock.due(), 10);
+        clock.ran(4);
+        assert_eq!(clock.due(), 6);
+    }
+
+    #[test]
+    fn speed_scales_the_rate() {
+        let mut clock = clock(Duration::from_millis(550));
+        clock.set_speed(2.0);
+        assert_eq!(clock.tps(), 20.0);
+        assert_eq!(clock.due(), 11);
+        assert_eq!(SimClock::new().with_tps(0.0).tps(), MIN_TPS);
+    }
+
+    #[test]
+    fn paused_clock_only_runs_the_steps() {
+        let mut clock = clock(Duration::from_secs(30));
+        clock.step(3);
+        assert!(clock.is_paused());
+        assert_eq!(clock.due(), 3);
+        clock.ran(2);
+        assert_eq!(clock.due(), 1);
+        clock.ran(1);
+        assert_eq!(clock.due(), 0);
+        clock.frame_rendered();
+        assert!(clock.wait_time().is_some());
+
+        // no time is owed for the pause
+        clock.toggle_pause();
+        assert_eq!(clock.due(), 0);
+    }
+
+    #[test]
+    fn turbo_is_unbounded() {
+        let mut clock = clock(Duration::ZERO);
+        clock.toggle_turbo();
+        assert_eq!(clock.due(), u64::MAX);
+        clock.ran(1_000_000);
+        assert_eq!(clock.wait_time(), None);
+        clock.set_speed(1.0);
+        assert!(!clock.is_turbo());
+        assert_eq!(clock.due(), 0);
+    }
+
+    #[test]
+    fn run_due_runs_the_owed_ticks() {
+        let mut clock = clock(Duration::from_millis(550));
+        let mut ticks = 0;
+        assert_eq!(clock.run_due(|| ticks += 1), 5);
+        assert_eq!(ticks, 5);
+        assert_eq!(clock.due(), 0);
+
+        // the first frame is already due
+        clock.toggle_turbo();
+        assert_eq!(clock.run_due(|| {}), 1);
+    }
+
+    #[test]
+    fn speed_doubles_within_bounds() {
+        let mut clock = SimClock::new();
+        clock.faster();
+        assert_eq!(clock.speed(), 2.0);
+        clock.slower();
+        clock.slower();
+        assert_eq!(clock.speed(), 0.5);
+        for _ in 0..20 {
+            clock.slower();
+        }
+        assert_eq!(clock.speed(), MIN_SPEED);
+        for _ in 0..40 {
+            clock.faster();
+        }
+        assert_eq!(clock.speed(), MAX_SPEED);
+        clock.set_speed(f64::INFINITY);
+        assert_eq!(clock.speed(), MAX_SPEED);
+    }
+
+    #[test]
+    fn steps_pause_and_add_up() {
+        let mut clock = SimClock::new();
+        clock.step(1);
+        clock.step(2);
+        assert!(clock.is_paused());
+        assert_eq!(clock.due(), 3);
+        clock.toggle_pause();
+        assert!(!clock.is_paused());
+        clock.toggle_pause();
+        assert!(clock.is_paused());
+    }
+
+    #[test]
+    fn state_is_shown_by_priority() {
+        let mut clock = SimClock::new();
+        clock.set_speed(0.25);
+        assert_eq!(clock.to_string(), "x0.25");
+        clock.toggle_turbo();
+        assert_eq!(clock.to_string(), "max");
+        clock.toggle_pause();
+        assert_eq!(clock.to_string(), "paused");
+        assert_eq!(
+            clock.state(),
+            ClockState {
+                paused: true,
+                turbo: true,
+                speed: 0.25
+            }
+        );
+        // a new speed leaves the turbo mode
+        clock.toggle_pause();
+        clock.faster();
+        assert!(!clock.is_turbo());
+        assert_eq!(clock.to_string(), "x0.5");
+    }
+}
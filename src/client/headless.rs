@@ -0,0 +1,105 @@
+use crate::{
+    client::{
+        clock::SimClock,
+        traits::{App, ClientError, EventHandler},
+    },
+    world::World,
+};
+
+/// Runs the simulation as fast as possible without a window, printing a
+/// status line every `report_interval` ticks
+pub struct AppHeadless {
+    world: World,
+    clock: SimClock,
+    /// Tick to stop at, runs forever if not set
+    ticks: Option<u64>,
+    report_interval: u64,
+}
+
+impl AppHeadless {
+    fn is_done(&self) -> bool {
+        self.ticks.is_some_and(|ticks| self.world.tick() >= ticks)
+    }
+
+    fn report(&self) {
+        let counters = self.world.counters();
+        println!(
+            "tick {}: cells {}, births {}, mutations {}, deaths {}",
+            self.world.tick(),
+            self.world.count_cells(),
+            counters.births,
+            counters.mutations,
+            counters.total_deaths()
+        );
+    }
+
+    /// Stops at `ticks` exactly, the rest of the turbo frame is idle
+    fn update(&mut self) {
+        if self.is_done() {
+            return;
+        }
+        self.world.update();
+        if self.world.tick().is_multiple_of(self.report_interval) {
+            self.report();
+        }
+    }
+
+    pub fn with_ticks(mut self, ticks: Option<u64>) -> Self {
+        self.ticks = ticks;
+        self
+    }
+
+    pub fn with_report_interval(mut self, report_interval: u64) -> Self {
+        self.report_interval = report_interval.max(1);
+        self
+    }
+}
+
+impl App for AppHeadless {
+    fn with_world(world: World) -> Self {
+        let mut clock = SimClock::new();
+        clock.toggle_turbo();
+        Self {
+            world,
+            clock,
+            ticks: None,
+            report_interval: 1000,
+        }
+    }
+
+    fn with_sim_clock(mut self, clock: SimClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn init(self) -> Result<Self, ClientError> {
+        Ok(self)
+    }
+
+    #[inline(always)]
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn render(&mut self) {}
+
+    /// Updates the world at the rate of the clock, turbo by default
+    fn run(mut self) -> Self {
+        let mut clock = std::mem::take(&mut self.clock);
+        while !self.event_handler() {
+            clock.run_due(|| self.update());
+            if clock.is_frame_due() {
+                clock.frame_rendered();
+            }
+            clock.wait();
+        }
+        self.clock = clock;
+        self
+    }
+}
+
+impl EventHandler for AppHeadless {
+    fn event_handler(&mut self) -> bool {
+        self.is_done()
+    }
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    genome::Gene,
+    render::{Legend, ModRender},
+    stats::gene_frequencies,
+    world::World,
+};
+
+/// Points of the population graph
+pub const HISTORY: usize = 300;
+
+/// Period of the measurement of the ticks per second
+const TPS_WINDOW: Duration = Duration::from_millis(500);
+/// Counting the genes or the legend categories of every cell is too slow
+/// for every frame
+const RECOUNT_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Live statistics of the overlay, sampled once per frame
+#[derive(Debug)]
+pub struct Hud {
+    visible: bool,
+    tps: f64,
+    window_start: (Instant, u64),
+    /// Tick and population, the oldest first
+    population: VecDeque<(u64, usize)>,
+    gene_frequencies: [f64; Gene::VARIANT_COUNT],
+    genes_updated: Option<Instant>,
+    /// Mode, time and legend of the last call of `legend`
+    legend: Option<(ModRender, Instant, Legend)>,
+}
+
+impl Hud {
+    pub fn new() -> Self {
+        Self {
+            visible: true,
+            tps: 0.0,
+            window_start: (Instant::now(), 0),
+            population: VecDeque::with_capacity(HISTORY),
+            gene_frequencies: [0.0; Gene::VARIANT_COUNT],
+            genes_updated: None,
+            legend: None,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    /// Ticks per second measured over the last `TPS_WINDOW`
+    #[inline(always)]
+    pub fn tps(&self) -> f64 {
+        self.tps
+    }
+
+    #[inline(always)]
+    pub fn population(&self) -> &VecDeque<(u64, usize)> {
+        &self.population
+    }
+
+    /// Indexed by `Gene::kind`
+    #[inline(always)]
+    pub fn gene_frequencies(&self) -> &[f64; Gene::VARIANT_COUNT] {
+        &self.gene_frequencies
+    }
+
+    /// Records the state of `world`, call once per frame
+    pub fn sample(&mut self, world: &World) {
+        let now = Instant::now();
+        let (start, start_tick) = self.window_start;
+        let tick = world.tick();
+        // a loaded checkpoint or a replay goes back in time
+        if tick < start_tick || self.population.back().is_some_and(|(last, _)| tick < *last) {
+            self.window_start = (now, tick);
+            self.population.clear();
+        } else if now.duration_since(start) >= TPS_WINDOW {
+            self.tps = (tick - start_tick) as f64 / now.duration_since(start).as_secs_f64();
+            self.window_start = (now, tick);
+        }
+
+        if self.population.back().is_none_or(|(last, _)| *last != tick) {
+            if self.population.len() == HISTORY {
+                self.population.pop_front();
+            }
+            self.population.push_back((tick, world.count_cells()));
+        }
+
+        if self.visible
+            && self
+                .genes_updated
+                .is_none_or(|updated| now.duration_since(updated) >= RECOUNT_INTERVAL)
+        {
+            self.gene_frequencies = gene_frequencies(world);
+            self.genes_updated = Some(now);
+        }
+    }
+
+    /// Legend of `mode`, recounted every `RECOUNT_INTERVAL` or when the
+    /// mode changes
+    pub fn legend(&mut self, mode: ModRender, world: &World) -> &Legend {
+        let now = Instant::now();
+        let stale = self.legend.as_ref().is_none_or(|(last, updated, _)| {
+            *last != mode || now.duration_since(*updated) >= RECOUNT_INTERVAL
+        });
+        if stale {
+            self.legend = Some((mode, now, mode.legend(world)));
+        }
+        &self.legend.as_ref().unwrap().2
+    }
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn legend_is_recounted_on_a_new_mode() {
+        let mut hud = Hud::new();
+        let mut world = testing::populated(testing::config(18), 6);
+        world.update();
+        let families = ModRender::Family.legend(&world);
+        assert_eq!(*hud.legend(ModRender::Family, &world), families);
+
+        // the frames in between reuse the counts
+        let empty = World::new(testing::config(18));
+        assert_eq!(*hud.legend(ModRender::Family, &empty), families);
+
+        assert_eq!(hud.legend(ModRender::Gene, &empty).title, "last gene");
+        assert!(hud.legend(ModRender::Family, &empty).entries.is_empty());
+    }
+
+    #[test]
+    fn population_is_sampled_once_per_tick() {
+        let mut hud = Hud::new();
+        let mut world = testing::populated(testing::config(19), 6);
+        hud.sample(&world);
+        hud.sample(&world);
+        assert_eq!(hud.population().len(), 1);
+        for _ in 0..3 {
+            world.update();
+            hud.sample(&world);
+        }
+        let ticks: Vec<u64> = hud.population().iter().map(|(tick, _)| *tick).collect();
+        assert_eq!(ticks, [0, 1, 2, 3]);
+        assert_eq!(hud.population().back().unwrap().1, world.count_cells());
+
+        // a restored earlier state starts a new graph
+        let earlier = testing::populated(testing::config(19), 6);
+        hud.sample(&earlier);
+        assert_eq!(hud.population().len(), 1);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut hud = Hud::new();
+        let mut world = World::new(testing::config(20));
+        for _ in 0..HISTORY + 10 {
+            world.update();
+            hud.sample(&world);
+        }
+        assert_eq!(hud.population().len(), HISTORY);
+        assert_eq!(hud.population().front().unwrap().0, 11);
+    }
+
+    #[test]
+    fn genes_are_counted_while_visible() {
+        let mut world = testing::populated(testing::config(21), 6);
+        world.update();
+
+        let mut hidden = Hud::new();
+        hidden.toggle();
+        assert!(!hidden.is_visible());
+        hidden.sample(&world);
+        assert_eq!(hidden.gene_frequencies().iter().sum::<f64>(), 0.0);
+
+        let mut hud = Hud::new();
+        hud.sample(&world);
+        assert_eq!(*hud.gene_frequencies(), gene_frequencies(&world));
+        assert!((hud.gene_frequencies().iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{
+    cell::{Cell, CellId},
+    math::Position,
+    world::World,
+};
+
+/// Farthest a cell moves in one tick: a step of the genome and the gravity
+const FOLLOW_RADIUS: i32 = 2;
+
+/// A cell chosen by the user, followed while it moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    id: CellId,
+    pos: Position,
+}
+
+impl Selection {
+    /// The cell at `pos`, if any
+    pub fn at(world: &World, pos: Position) -> Option<Self> {
+        world.get(pos).map(|cell| Self { id: cell.id, pos })
+    }
+
+    #[inline(always)]
+    pub fn id(&self) -> CellId {
+        self.id
+    }
+
+    /// Last known position
+    #[inline(always)]
+    pub fn pos(&self) -> Position {
+        self.pos
+    }
+
+    /// Finds the cell again after the updates, `None` once it is dead
+    pub fn follow<'a>(&mut self, world: &'a World) -> Option<&'a Cell> {
+        if let Some(cell) = world.get(self.pos).filter(|cell| cell.id == self.id) {
+            return Some(cell);
+        }
+
+        let near = (-FOLLOW_RADIUS..=FOLLOW_RADIUS)
+            .flat_map(|dy| (-FOLLOW_RADIUS..=FOLLOW_RADIUS).map(move |dx| (dx, dy)))
+            .map(|d| self.pos + d)
+            .find_map(|pos| {
+                world
+                    .get(pos)
+                    .filter(|cell| cell.id == self.id)
+                    .map(|_| pos)
+            });
+        // several ticks per frame, the cell may be anywhere
+        let pos = near.or_else(|| {
+            world
+                .iter()
+                .find(|(_, cell)| cell.id == self.id)
+                .map(|(pos, _)| *pos)
+        })?;
+
+        self.pos = pos;
+        world.get(pos)
+    }
+}
+
+/// Lines of the inspection panel, the genome is listed by `Disassembly`
+pub fn describe(cell: &Cell, pos: Position) -> Vec<String> {
+    vec![
+        format!("cell #{}", cell.id),
+        format!("parent #{} clade #{}", cell.parent, cell.clade),
+        format!("pos {} {}", pos.x(), pos.y()),
+        format!("family {}", cell.family),
+        format!("energy {:.3}", cell.energy),
+        format!("health {:.3}", cell.health),
+        format!("toxin {:.3}", cell.toxin),
+        format!("lifetime {}/{}", cell.lifetime, cell.max_lifetime),
+        format!("color {} {} {}", cell.color.0, cell.color.1, cell.color.2),
+        format!(
+            "genome {} genes, step {}",
+            cell.genome.len(),
+            cell.genome.step
+        ),
+        format!("mutation rate {:.5}", cell.mutation.cell),
+        match cell.last_gene {
+            Some(gene) => format!("last gene {}", gene),
+            None => "last gene -".to_string(),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{pos, testing};
+
+    /// A world of one cell at (10, 10) after an update
+    fn world() -> World {
+        testing::one_cell(15, pos!(10, 10))
+    }
+
+    /// `world` with its only cell at `to`
+    fn moved(world: &World, to: Position) -> World {
+        let mut snapshot = world.snapshot();
+        snapshot.cells[0].0 = to;
+        World::from_snapshot(snapshot).unwrap()
+    }
+
+    #[test]
+    fn selection_needs_a_cell() {
+        let world = world();
+        assert_eq!(Selection::at(&world, pos!(11, 10)), None);
+        let selection = Selection::at(&world, pos!(10, 10)).unwrap();
+        assert_eq!(selection.id(), 1);
+        assert_eq!(selection.pos(), pos!(10, 10));
+    }
+
+    #[test]
+    fn selection_follows_the_cell() {
+        let world = world();
+        let mut selection = Selection::at(&world, pos!(10, 10)).unwrap();
+
+        // nearby and far away
+        for to in [pos!(12, 9), pos!(40, 30)] {
+            let world = moved(&world, to);
+            assert_eq!(selection.follow(&world).map(|cell| cell.id), Some(1));
+            assert_eq!(selection.pos(), to);
+        }
+
+        // another cell at the last position is not the selected one
+        let mut snapshot = world.snapshot();
+        snapshot.cells[0].1.id = 2;
+        let replaced = World::from_snapshot(snapshot).unwrap();
+        assert!(selection.follow(&replaced).is_none());
+        assert_eq!(selection.pos(), pos!(40, 30));
+    }
+
+    #[test]
+    fn panel_lines() {
+        let mut world = world();
+        let mut selection = Selection::at(&world, pos!(10, 10)).unwrap();
+        // a cell spawned before an update has not run a gene yet
+        let cell = selection.follow(&world).unwrap();
+        assert_eq!(describe(cell, pos!(10, 10)).last().unwrap(), "last gene -");
+
+        world.update();
+        let cell = selection.follow(&world).unwrap();
+        let pos = selection.pos();
+        let lines = describe(cell, pos);
+        assert_eq!(lines[0], "cell #1");
+        assert_eq!(lines[1], "parent #0 clade #1");
+        assert_eq!(lines[2], format!("pos {} {}", pos.x(), pos.y()));
+        assert!(lines.iter().any(|line| line.starts_with("mutation rate ")));
+        let last = lines.last().unwrap();
+        assert_eq!(*last, format!("last gene {}", cell.last_gene.unwrap()));
+    }
+}
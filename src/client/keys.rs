@@ -0,0 +1,182 @@
+use crate::{
+    client::{camera::Camera, clock::SimClock, hud::Hud},
+    render::ModRender,
+    world::World,
+};
+
+/// Ticks run by Shift+Right
+pub const STEP_MANY: u64 = 100;
+
+/// Actions bound to the same keys in every client
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Command {
+    Render(ModRender),
+    NextRender,
+    /// Multiplier of the ticks per second, see `SimClock::with_tps`
+    Speed(f64),
+    Slower,
+    Faster,
+    ToggleTurbo,
+    TogglePause,
+    /// Pauses and runs the ticks
+    Step(u64),
+    ToggleHud,
+    /// Shows the whole world
+    Fit,
+}
+
+impl Command {
+    /// Handled by `apply_to_clock`, the others by `apply_to_view`
+    pub fn is_clock(&self) -> bool {
+        matches!(
+            self,
+            Command::Speed(_)
+                | Command::Slower
+                | Command::Faster
+                | Command::ToggleTurbo
+                | Command::TogglePause
+                | Command::Step(_)
+        )
+    }
+
+    /// Applies the commands of the clock, returns false for the others
+    pub fn apply_to_clock(&self, clock: &mut SimClock) -> bool {
+        match *self {
+            Command::Speed(speed) => clock.set_speed(speed),
+            Command::Slower => clock.slower(),
+            Command::Faster => clock.faster(),
+            Command::ToggleTurbo => clock.toggle_turbo(),
+            Command::TogglePause => clock.toggle_pause(),
+            Command::Step(ticks) => clock.step(ticks),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Applies the commands of the view, returns false for the others
+    pub fn apply_to_view(
+        &self,
+        mod_render: &mut ModRender,
+        hud: &mut Hud,
+        camera: &mut Camera,
+        world: &World,
+    ) -> bool {
+        match *self {
+            Command::Render(mode) => *mod_render = mode,
+            Command::NextRender => *mod_render = mod_render.next(),
+            Command::ToggleHud => hud.toggle(),
+            Command::Fit => camera.fit(world.width(), world.height()),
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Command of a key, named like SDL does: upper case letters, "Space", "Right", "Tab"
+pub fn command(key: &str, shift: bool) -> Option<Command> {
+    let command = match key {
+        "D" => Command::Render(ModRender::Default),
+        "E" => Command::Render(ModRender::Energy),
+        "T" => Command::Render(ModRender::Toxin),
+        "H" => Command::Render(ModRender::Health),
+        "K" => Command::Render(ModRender::Family),
+        "A" => Command::Render(ModRender::Lifetime),
+        "N" => Command::Render(ModRender::Genotype),
+        "L" => Command::Render(ModRender::Gene),
+        "S" => Command::Render(ModRender::Step),
+        "Tab" => Command::NextRender,
+        "O" => Command::ToggleHud,
+        "1" => Command::Speed(1.0),
+        "2" => Command::Speed(2.0),
+        "3" => Command::Speed(4.0),
+        "4" => Command::Speed(8.0),
+        "5" => Command::Speed(16.0),
+        "6" => Command::Speed(32.0),
+        "7" => Command::Speed(64.0),
+        "-" => Command::Slower,
+        "=" => Command::Faster,
+        "0" => Command::ToggleTurbo,
+        "Space" => Command::TogglePause,
+        "Right" if shift => Command::Step(STEP_MANY),
+        "Right" => Command::Step(1),
+        "F" => Command::Fit,
+        _ => return None,
+    };
+    Some(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    const KEYS: [&str; 26] = [
+        "D", "E", "T", "H", "K", "A", "N", "L", "S", "Tab", "O", "1", "2", "3", "4", "5", "6", "7",
+        "-", "=", "0", "Space", "Right", "F", "Q", "Escape",
+    ];
+
+    #[test]
+    fn keys_of_the_commands() {
+        assert_eq!(
+            command("E", false),
+            Some(Command::Render(ModRender::Energy))
+        );
+        assert_eq!(command("3", false), Some(Command::Speed(4.0)));
+        assert_eq!(command("Right", false), Some(Command::Step(1)));
+        assert_eq!(command("Right", true), Some(Command::Step(STEP_MANY)));
+        assert_eq!(command("Space", true), Some(Command::TogglePause));
+        // quitting and the selection are left to the clients
+        assert_eq!(command("Q", false), None);
+        assert_eq!(command("Escape", false), None);
+        assert_eq!(command("e", false), None);
+    }
+
+    #[test]
+    fn every_command_is_applied_once() {
+        let world = World::new(testing::config(22));
+        for key in KEYS {
+            let Some(command) = command(key, false) else {
+                continue;
+            };
+            let mut clock = SimClock::new();
+            let (mut mod_render, mut hud) = (ModRender::Default, Hud::new());
+            let mut camera = Camera::new((100, 100), 1.0);
+            let on_clock = command.apply_to_clock(&mut clock);
+            let on_view = command.apply_to_view(&mut mod_render, &mut hud, &mut camera, &world);
+            assert_eq!(on_clock, command.is_clock(), "{}", key);
+            assert_ne!(on_clock, on_view, "{}", key);
+        }
+    }
+
+    #[test]
+    fn commands_change_the_clock_and_the_view() {
+        let mut clock = SimClock::new();
+        command("4", false).unwrap().apply_to_clock(&mut clock);
+        assert_eq!(clock.speed(), 8.0);
+        command("-", false).unwrap().apply_to_clock(&mut clock);
+        assert_eq!(clock.speed(), 4.0);
+        command("Right", true).unwrap().apply_to_clock(&mut clock);
+        assert!(clock.is_paused());
+        assert_eq!(clock.due(), STEP_MANY);
+
+        let world = World::new(testing::config(22));
+        let (mut mod_render, mut hud) = (ModRender::Default, Hud::new());
+        let mut camera = Camera::new((100, 100), 1.0);
+        let mut apply = |key| {
+            command(key, false).unwrap().apply_to_view(
+                &mut mod_render,
+                &mut hud,
+                &mut camera,
+                &world,
+            )
+        };
+        apply("Tab");
+        apply("O");
+        apply("F");
+        assert_eq!(mod_render, ModRender::Energy);
+        assert!(!hud.is_visible());
+        let mut fitted = Camera::new((100, 100), 1.0);
+        fitted.fit(world.width(), world.height());
+        assert_eq!(camera, fitted);
+    }
+}
@@ -1,4 +1,14 @@
+pub mod brush;
+pub mod camera;
+pub mod clock;
+pub mod headless;
+pub mod hud;
+pub mod inspect;
+pub mod keys;
+pub mod sim;
 pub mod traits;
 
 #[cfg(feature = "sdl3")]
 pub mod sdl;
+#[cfg(feature = "term")]
+pub mod term;
@@ -0,0 +1,95 @@
+use sdl3::{
+    pixels::Color,
+    rect::Rect,
+    render::{BlendMode, Canvas, FPoint},
+    video::Window,
+};
+
+/// Size of the glyphs of `Canvas::draw_debug_text`
+pub(super) const CHAR_WIDTH: i32 = 8;
+pub(super) const LINE_HEIGHT: i32 = 10;
+pub(super) const PADDING: i32 = 8;
+
+pub(super) const PANEL_COLOR: Color = Color::RGBA(10, 10, 14, 220);
+pub(super) const TEXT_COLOR: Color = Color::RGB(220, 220, 220);
+pub(super) const DIM_COLOR: Color = Color::RGB(110, 110, 110);
+
+/// Translucent background of an overlay
+pub(super) fn panel(canvas: &mut Canvas<Window>, rect: Rect) -> Result<(), sdl3::Error> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    canvas.set_draw_color(PANEL_COLOR);
+    canvas.fill_rect(rect)?;
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// `(x, y)` is the top left corner of the first glyph
+pub(super) fn text<C: Into<Color>>(
+    canvas: &mut Canvas<Window>,
+    text: &str,
+    x: i32,
+    y: i32,
+    color: C,
+) -> Result<(), sdl3::Error> {
+    canvas.set_draw_color(color);
+    canvas.draw_debug_text(text, (x as f32, y as f32))
+}
+
+/// Small rectangle of a colour key, as high as a glyph
+pub(super) fn swatch<C: Into<Color>>(
+    canvas: &mut Canvas<Window>,
+    x: i32,
+    y: i32,
+    color: C,
+) -> Result<(), sdl3::Error> {
+    canvas.set_draw_color(color);
+    canvas.fill_rect(Rect::new(x, y, (CHAR_WIDTH * 2) as u32, CHAR_WIDTH as u32))
+}
+
+/// Values from `0` to `max` stretched over the width of `rect`, the first one on the left
+pub(super) fn line_graph<C: Into<Color>>(
+    canvas: &mut Canvas<Window>,
+    rect: Rect,
+    values: &[f32],
+    max: f32,
+    color: C,
+) -> Result<(), sdl3::Error> {
+    canvas.set_draw_color(DIM_COLOR);
+    canvas.draw_rect(rect)?;
+    if values.len() < 2 || max <= 0.0 {
+        return Ok(());
+    }
+
+    let (left, bottom) = (rect.x() as f32, rect.bottom() as f32 - 1.0);
+    let step = (rect.width() - 1) as f32 / (values.len() - 1) as f32;
+    let height = (rect.height() - 1) as f32;
+    let points: Vec<FPoint> = values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            FPoint::new(
+                left + i as f32 * step,
+                bottom - (value / max).clamp(0.0, 1.0) * height,
+            )
+        })
+        .collect();
+    canvas.set_draw_color(color);
+    canvas.draw_lines(points.as_slice())
+}
+
+/// Horizontal bar filled to `fraction` of `rect`
+pub(super) fn bar<C: Into<Color>>(
+    canvas: &mut Canvas<Window>,
+    rect: Rect,
+    fraction: f32,
+    color: C,
+) -> Result<(), sdl3::Error> {
+    canvas.set_draw_color(DIM_COLOR);
+    canvas.draw_rect(rect)?;
+    let width = (rect.width() as f32 * fraction.clamp(0.0, 1.0)).round() as u32;
+    if width > 0 {
+        canvas.set_draw_color(color);
+        canvas.fill_rect(Rect::new(rect.x(), rect.y(), width, rect.height()))?;
+    }
+    Ok(())
+}
@@ -1,143 +1,275 @@
-use std::time::Duration;
+use std::any::Any;
 
-use sdl3::{event::Event, pixels::Color, rect::Rect};
+use sdl3::{
+    event::{Event, WindowEvent},
+    keyboard::Mod,
+    mouse::MouseButton,
+    pixels::Color,
+    rect::Rect,
+};
 
 use crate::{
-    cell::Cell,
-    client::traits::{App, EventHandler},
+    client::{
+        brush::{Brush, Clipboard, Tool},
+        camera::Camera,
+        clock::{ClockState, SimClock},
+        hud::Hud,
+        inspect::Selection,
+        keys::{self, Command},
+        sim::{SimStopped, SimThread},
+        traits::{App, ClientError, EventHandler},
+    },
     math::Position,
     pos,
+    render::{BACKGROUND, ModRender, WALL},
     world::World,
 };
 
+mod draw;
+mod panel;
+
+const SELECTION_COLOR: Color = Color::RGB(255, 255, 255);
+
+/// Zoom factor of one step of the mouse wheel
+const ZOOM_STEP: f32 = 1.25;
+
 pub struct AppSdl {
     pub title: &'static str,
     sdl_ctx: Option<sdl3::Sdl>,
     video_subsystem: Option<sdl3::VideoSubsystem>,
     canvas: Option<sdl3::render::Canvas<sdl3::video::Window>>,
     event_pump: Option<sdl3::EventPump>,
+    /// Updated on the simulation thread, this one is the latest published
+    /// copy while it runs
     world: World,
-    scale: i32,
-    speed: usize,
+    sim: Option<SimThread>,
+    /// Of the simulation thread
+    sim_clock: ClockState,
+    /// Given to the simulation thread by `init`
+    sim_scheduler: Option<SimClock>,
+    /// The simulation thread stopped on its own, the loop is stopped
+    sim_stopped: bool,
+    /// Resumed by the caller of `run` once the outputs are saved
+    sim_panic: Option<Box<dyn Any + Send>>,
+    camera: Camera,
     mod_render: ModRender,
+    selection: Option<Selection>,
+    hud: Hud,
+    brush: Brush,
+    /// Start and last position of the drag with the left button
+    drag: Option<(Position, Position)>,
 }
 
 impl App for AppSdl {
-    fn new() -> Self {
+    fn with_world(world: World) -> Self {
         Self {
             title: "EvoCell",
             sdl_ctx: None,
             video_subsystem: None,
             canvas: None,
             event_pump: None,
-            world: World::new(),
-            scale: 2,
-            speed: 1,
+            world,
+            sim: None,
+            sim_clock: SimClock::new().state(),
+            sim_scheduler: None,
+            sim_stopped: false,
+            sim_panic: None,
+            camera: Camera::new((800, 600), 2.0),
             mod_render: ModRender::Default,
+            selection: None,
+            hud: Hud::new(),
+            brush: Brush::new(),
+            drag: None,
         }
     }
 
-    fn init(mut self) -> Self {
-        let sdl_context = sdl3::init().unwrap();
-        let video_subsystem = sdl_context.video().unwrap();
+    fn with_sim_clock(mut self, clock: SimClock) -> Self {
+        self.sim_clock = clock.state();
+        self.sim_scheduler = Some(clock);
+        self
+    }
+
+    fn init(mut self) -> Result<Self, ClientError> {
+        let sdl_context = sdl3::init().map_err(backend)?;
+        let video_subsystem = sdl_context.video().map_err(backend)?;
 
         let window = video_subsystem
             .window(self.title, 800, 600)
             .position_centered()
+            .resizable()
             .opengl()
             .build()
-            .map_err(|e| e.to_string())
-            .unwrap();
+            .map_err(backend)?;
 
         let canvas = window.into_canvas();
+        let (width, height) = canvas.output_size().map_err(backend)?;
+        self.camera = Camera::new((width, height), self.camera.zoom());
+        self.camera.fit(self.world.width(), self.world.height());
 
+        self.event_pump = Some(sdl_context.event_pump().map_err(backend)?);
         self.sdl_ctx = Some(sdl_context);
         self.video_subsystem = Some(video_subsystem);
         self.canvas = Some(canvas);
-        self.event_pump = Some(self.sdl_ctx.as_ref().unwrap().event_pump().unwrap());
 
-        self.world.add(pos!(50, 100), Cell::new());
+        let view = self.world.view();
+        let world = std::mem::replace(&mut self.world, view);
+        self.sim = Some(SimThread::spawn(
+            world,
+            self.sim_scheduler.take().unwrap_or_default(),
+        )?);
 
-        self
+        Ok(self)
+    }
+
+    #[inline(always)]
+    fn world(&self) -> &World {
+        &self.world
     }
 
     fn render(&mut self) {
+        match self.sim.as_ref().map(SimThread::latest) {
+            Some(Ok(Some(frame))) => {
+                self.world = frame.world;
+                self.sim_clock = frame.clock;
+            }
+            Some(Err(SimStopped)) => self.sim_stopped = true,
+            _ => {}
+        }
+
         let canvas = self.canvas.as_mut().unwrap();
-        let _ = canvas.window_mut().set_title(
-            format!(
-                "Cells count: {}; Mod render: {:?}; Speed: x{}",
-                self.world.count_cells(),
-                self.mod_render,
-                self.speed
-            )
-            .as_str(),
-        );
-        canvas.set_draw_color(Color::RGB(25, 25, 30));
+        canvas.set_draw_color(BACKGROUND);
         canvas.clear();
 
-        for (pos, cell) in self.world.iter() {
-            match self.mod_render {
-                ModRender::Default => canvas.set_draw_color(cell.color),
-                ModRender::Energy => canvas.set_draw_color((
-                    (cell.energy * 10.0) as u8,
-                    (cell.energy * 10.0) as u8,
-                    (cell.energy * 10.0) as u8,
-                )),
-                ModRender::Toxin => canvas.set_draw_color((
-                    (cell.toxin * 10.0) as u8,
-                    (cell.toxin * 10.0) as u8,
-                    (cell.toxin * 10.0) as u8,
-                )),
-                ModRender::Health => canvas.set_draw_color((
-                    (cell.health * 10.0) as u8,
-                    (cell.health * 10.0) as u8,
-                    (cell.health * 10.0) as u8,
-                )),
+        let camera = self.camera;
+        let (min, max) = camera.visible(self.world.width(), self.world.height());
+        canvas.set_draw_color(WALL);
+        for y in min.y()..max.y() {
+            for x in min.x()..max.x() {
+                if self.world.is_wall(pos!(x, y)) {
+                    canvas.fill_rect(cell_rect(&camera, pos!(x, y))).unwrap();
+                }
+            }
+        }
+
+        let (mod_render, config) = (self.mod_render, &self.world.config().cell);
+        camera.for_each_visible(&self.world, |pos, cell| {
+            canvas.set_draw_color(mod_render.color(cell, config));
+            canvas.fill_rect(cell_rect(&camera, pos)).unwrap();
+        });
+
+        if let (Tool::Copy, Some((start, last))) = (self.brush.tool, self.drag) {
+            let (a, b) = (cell_rect(&camera, start), cell_rect(&camera, last));
+            canvas.set_draw_color(SELECTION_COLOR);
+            canvas.draw_rect(a.union(b)).unwrap();
+        }
+
+        if let Some(selection) = &mut self.selection {
+            match selection.follow(&self.world) {
+                Some(cell) => {
+                    canvas.set_draw_color(SELECTION_COLOR);
+                    canvas
+                        .draw_rect(cell_rect(&camera, selection.pos()))
+                        .unwrap();
+                    panel::draw_inspector(canvas, cell, selection.pos()).unwrap();
+                }
+                None => self.selection = None,
             }
+        }
 
-            canvas
-                .fill_rect(Rect::new(
-                    pos.x() * self.scale,
-                    pos.y() * self.scale,
-                    self.scale as u32,
-                    self.scale as u32,
-                ))
-                .unwrap();
+        self.hud.sample(&self.world);
+        if self.hud.is_visible() {
+            let status = [
+                format!(
+                    "tick {}  tps {:.1}  {}",
+                    self.world.tick(),
+                    self.hud.tps(),
+                    self.sim_clock
+                ),
+                format!(
+                    "cells {}  mode {}",
+                    self.world.count_cells(),
+                    self.mod_render
+                ),
+                format!("tool {} r{}", self.brush.tool, self.brush.radius),
+            ];
+            panel::draw_hud(canvas, &self.hud, &status).unwrap();
+            let legend = self.hud.legend(self.mod_render, &self.world);
+            panel::draw_legend(canvas, legend).unwrap();
         }
 
         canvas.present();
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
     }
 
-    fn update(&mut self) {
-        for _ in 0..self.speed {
-            self.world.update();
-        }
+    fn take_sim_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+        self.sim_panic.take()
     }
 }
 
 impl EventHandler for AppSdl {
     fn event_handler(&mut self) -> bool {
-        for event in self.event_pump.as_mut().unwrap().poll_iter() {
+        if self.sim_stopped {
+            self.stop_sim();
+            return true;
+        }
+
+        let events: Vec<Event> = self.event_pump.as_mut().unwrap().poll_iter().collect();
+        // the mouse is in window coordinates and the camera in pixels
+        let density = self.pixel_density();
+        for event in events {
             match event {
-                Event::KeyDown { keycode, .. } => {
-                    if let Some(k) = keycode {
-                        match k.name().as_str() {
-                            "D" => self.mod_render = ModRender::Default,
-                            "E" => self.mod_render = ModRender::Energy,
-                            "H" => self.mod_render = ModRender::Health,
-                            "1" => self.speed = 1,
-                            "2" => self.speed = 2,
-                            "3" => self.speed = 4,
-                            "4" => self.speed = 8,
-                            "5" => self.speed = 16,
-                            "6" => self.speed = 32,
-                            "7" => self.speed = 64,
-                            _ => {}
-                        }
+                Event::KeyDown {
+                    keycode: Some(k),
+                    keymod,
+                    ..
+                } => {
+                    let name = k.name();
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+                    match keys::command(&name, shift) {
+                        Some(command) => self.command(command),
+                        None => self.brush_key(&name),
                     }
                 }
-                Event::Quit { .. } => return true,
+                Event::MouseButtonDown {
+                    mouse_btn: MouseButton::Left,
+                    x,
+                    y,
+                    ..
+                } => self.mouse_down(self.camera.to_world(x * density, y * density)),
+                Event::MouseMotion {
+                    mousestate, x, y, ..
+                } if mousestate.left() => {
+                    self.mouse_drag(self.camera.to_world(x * density, y * density))
+                }
+                Event::MouseButtonUp {
+                    mouse_btn: MouseButton::Left,
+                    ..
+                } => self.mouse_up(),
+                Event::MouseWheel {
+                    y,
+                    mouse_x,
+                    mouse_y,
+                    ..
+                } => self
+                    .camera
+                    .zoom_at(ZOOM_STEP.powf(y), mouse_x * density, mouse_y * density),
+                Event::MouseMotion {
+                    mousestate,
+                    xrel,
+                    yrel,
+                    ..
+                } if mousestate.right() || mousestate.middle() => {
+                    self.camera.pan(xrel * density, yrel * density)
+                }
+                Event::Window {
+                    win_event: WindowEvent::PixelSizeChanged(width, height),
+                    ..
+                } => self
+                    .camera
+                    .set_viewport(width.max(1) as u32, height.max(1) as u32),
+                Event::Quit { .. } => {
+                    self.stop_sim();
+                    return true;
+                }
                 _ => {}
             }
         }
@@ -146,10 +278,117 @@ impl EventHandler for AppSdl {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
-enum ModRender {
-    Default,
-    Energy,
-    Toxin,
-    Health,
+impl AppSdl {
+    fn command(&mut self, command: Command) {
+        if command.is_clock() {
+            self.with_sim(|sim| sim.command(command));
+            return;
+        }
+        command.apply_to_view(
+            &mut self.mod_render,
+            &mut self.hud,
+            &mut self.camera,
+            &self.world,
+        );
+    }
+
+    /// Keys of the selection and the editing tools, only the SDL client has them
+    fn brush_key(&mut self, name: &str) {
+        match name {
+            "Escape" => self.selection = None,
+            "I" => self.brush.tool = Tool::Inspect,
+            "B" => {
+                // paints the genome of the inspected cell
+                self.brush.tool = Tool::Paint;
+                self.brush.genome = self
+                    .selection
+                    .and_then(|selection| self.world.get(selection.pos()))
+                    .map(|cell| cell.genome);
+            }
+            "X" => self.brush.tool = Tool::Erase,
+            "W" => self.brush.tool = Tool::Wall,
+            "G" => self.brush.tool = Tool::Energy,
+            "C" => self.brush.tool = Tool::Copy,
+            "V" => self.brush.tool = Tool::Paste,
+            "[" => self.brush.shrink(),
+            "]" => self.brush.grow(),
+            _ => {}
+        }
+    }
+
+    /// Pixels per window coordinate, above 1 on the HiDPI displays
+    fn pixel_density(&self) -> f32 {
+        self.canvas
+            .as_ref()
+            .map_or(1.0, |canvas| canvas.window().pixel_density())
+    }
+
+    /// The observers and the final checkpoint need the real world
+    fn stop_sim(&mut self) {
+        if let Some(sim) = self.sim.take() {
+            match sim.stop() {
+                Ok(world) => self.world = world,
+                // the last published copy is saved instead
+                Err(panic) => self.sim_panic = Some(panic),
+            }
+        }
+    }
+
+    /// The thread runs from `init` until the window is closed
+    fn with_sim<F: FnOnce(&SimThread)>(&self, f: F) {
+        if let Some(sim) = &self.sim {
+            f(sim);
+        }
+    }
+
+    fn mouse_down(&mut self, pos: Position) {
+        match self.brush.tool {
+            Tool::Inspect => self.selection = Selection::at(&self.world, pos),
+            Tool::Paste => {
+                let clipboard = self.brush.clipboard.clone();
+                self.with_sim(|sim| sim.edit(move |world| clipboard.paste(world, pos)));
+            }
+            Tool::Copy => {}
+            _ => {
+                let brush = self.brush.without_clipboard();
+                self.with_sim(|sim| sim.edit(move |world| brush.apply(world, pos)));
+            }
+        }
+        self.drag = Some((pos, pos));
+    }
+
+    fn mouse_drag(&mut self, pos: Position) {
+        let Some((start, last)) = self.drag else {
+            return;
+        };
+        if self.brush.tool.is_stroke() && pos != last {
+            let brush = self.brush.without_clipboard();
+            self.with_sim(|sim| sim.edit(move |world| brush.stroke(world, last, pos)));
+        }
+        self.drag = Some((start, pos));
+    }
+
+    fn mouse_up(&mut self) {
+        if let (Tool::Copy, Some((start, last))) = (self.brush.tool, self.drag.take()) {
+            self.brush.clipboard = Clipboard::copy(&self.world, start, last);
+        }
+    }
+}
+
+#[inline]
+fn backend<E: ToString>(e: E) -> ClientError {
+    ClientError::Backend(e.to_string())
+}
+
+/// Neighbouring cells share the edges, so there are no gaps at a fractional zoom
+fn cell_rect(camera: &Camera, pos: Position) -> Rect {
+    let (x0, y0) = camera.to_screen(pos);
+    let (x1, y1) = camera.to_screen(pos + (1, 1));
+    let (x0, y0) = (x0.floor(), y0.floor());
+    Rect::new(
+        x0 as i32,
+        y0 as i32,
+        ((x1.floor() - x0) as u32).max(1),
+        ((y1.floor() - y0) as u32).max(1),
+    )
 }
@@ -0,0 +1,181 @@
+use sdl3::{pixels::Color, rect::Rect, render::Canvas, video::Window};
+
+use super::draw::{
+    self, CHAR_WIDTH, DIM_COLOR, LINE_HEIGHT, PADDING, TEXT_COLOR, bar, line_graph, swatch, text,
+};
+use crate::{
+    cell::Cell,
+    client::{hud::Hud, inspect::describe},
+    colormap::qualitative,
+    genome::{Gene, disasm::Disassembly},
+    math::Position,
+    render::Legend,
+};
+
+/// Fits a dead gene: "  0  divide right_down     ; dead"
+const PANEL_CHARS: i32 = 32;
+/// Fits a genotype: "##  a1b2c3d4 (12345)"
+const LEGEND_CHARS: i32 = 24;
+/// Fits the status line: "tick 123456789  tps 12345.6  x1024"
+const HUD_CHARS: i32 = 36;
+const GRAPH_HEIGHT: i32 = 48;
+/// Fits the longest gene kind: "move_position"
+const GENE_NAME_CHARS: i32 = 14;
+
+const CURRENT_COLOR: Color = Color::RGB(70, 70, 150);
+const GRAPH_COLOR: Color = Color::RGB(120, 200, 120);
+
+/// Properties and genome of the inspected cell on the right side of the canvas.
+///
+/// When the genome does not fit, the listing scrolls with the current gene.
+pub(super) fn draw_inspector(
+    canvas: &mut Canvas<Window>,
+    cell: &Cell,
+    pos: Position,
+) -> Result<(), sdl3::Error> {
+    let (width, height) = canvas.output_size()?;
+    let panel_width = PANEL_CHARS * CHAR_WIDTH + PADDING * 2;
+    let left = width as i32 - panel_width;
+    draw::panel(canvas, Rect::new(left, 0, panel_width as u32, height))?;
+
+    let x = left + PADDING;
+    let mut y = PADDING;
+    for line in describe(cell, pos) {
+        text(canvas, &line, x, y, TEXT_COLOR)?;
+        y += LINE_HEIGHT;
+    }
+
+    // swatch next to the colour line
+    swatch(
+        canvas,
+        left + panel_width - PADDING - CHAR_WIDTH * 2,
+        PADDING + LINE_HEIGHT * 8,
+        cell.color,
+    )?;
+    y += LINE_HEIGHT;
+
+    let disassembly = Disassembly::new(&cell.genome);
+    let rows = ((height as i32 - y - PADDING) / LINE_HEIGHT).max(1) as usize;
+    let first = cell
+        .genome
+        .step
+        .saturating_sub(rows / 2)
+        .min(disassembly.lines.len().saturating_sub(rows));
+    for line in disassembly.lines.iter().skip(first).take(rows) {
+        if line.current {
+            canvas.set_draw_color(CURRENT_COLOR);
+            canvas.fill_rect(Rect::new(
+                left,
+                y - 1,
+                panel_width as u32,
+                LINE_HEIGHT as u32,
+            ))?;
+        }
+        let color = if line.reachable {
+            TEXT_COLOR
+        } else {
+            DIM_COLOR
+        };
+        text(canvas, &line.to_string(), x, y, color)?;
+        y += LINE_HEIGHT;
+    }
+
+    Ok(())
+}
+
+/// Colours of the render mode in the bottom left corner of the canvas
+pub(super) fn draw_legend(canvas: &mut Canvas<Window>, legend: &Legend) -> Result<(), sdl3::Error> {
+    let (_, height) = canvas.output_size()?;
+    let rows = 1 + legend.entries.len() as i32;
+    let (panel_width, panel_height) = (
+        LEGEND_CHARS * CHAR_WIDTH + PADDING * 2,
+        rows * LINE_HEIGHT + PADDING * 2,
+    );
+    let top = height as i32 - panel_height;
+    draw::panel(
+        canvas,
+        Rect::new(0, top, panel_width as u32, panel_height as u32),
+    )?;
+
+    let mut y = top + PADDING;
+    text(canvas, legend.title, PADDING, y, TEXT_COLOR)?;
+    for (label, color) in &legend.entries {
+        y += LINE_HEIGHT;
+        swatch(canvas, PADDING, y, *color)?;
+        text(canvas, label, PADDING + CHAR_WIDTH * 4, y, TEXT_COLOR)?;
+    }
+
+    Ok(())
+}
+
+/// `status` lines, the population graph and the gene frequencies in the top
+/// left corner of the canvas
+pub(super) fn draw_hud(
+    canvas: &mut Canvas<Window>,
+    hud: &Hud,
+    status: &[String],
+) -> Result<(), sdl3::Error> {
+    let inner_width = HUD_CHARS * CHAR_WIDTH;
+    let panel_height = PADDING * 2
+        + (status.len() as i32 + 2 + Gene::VARIANT_COUNT as i32) * LINE_HEIGHT
+        + GRAPH_HEIGHT
+        + PADDING;
+    draw::panel(
+        canvas,
+        Rect::new(
+            0,
+            0,
+            (inner_width + PADDING * 2) as u32,
+            panel_height as u32,
+        ),
+    )?;
+
+    let mut y = PADDING;
+    for line in status {
+        text(canvas, line, PADDING, y, TEXT_COLOR)?;
+        y += LINE_HEIGHT;
+    }
+
+    let population: Vec<f32> = hud.population().iter().map(|(_, n)| *n as f32).collect();
+    let max = population.iter().copied().fold(0.0, f32::max);
+    let span = match (hud.population().front(), hud.population().back()) {
+        (Some((first, _)), Some((last, _))) => last - first,
+        _ => 0,
+    };
+    text(
+        canvas,
+        &format!("population, max {} over {} ticks", max, span),
+        PADDING,
+        y,
+        DIM_COLOR,
+    )?;
+    y += LINE_HEIGHT;
+    line_graph(
+        canvas,
+        Rect::new(PADDING, y, inner_width as u32, GRAPH_HEIGHT as u32),
+        &population,
+        max,
+        GRAPH_COLOR,
+    )?;
+    y += GRAPH_HEIGHT + PADDING;
+
+    text(canvas, "gene frequencies", PADDING, y, DIM_COLOR)?;
+    let bar_left = PADDING + GENE_NAME_CHARS * CHAR_WIDTH;
+    for (kind, frequency) in hud.gene_frequencies().iter().enumerate() {
+        y += LINE_HEIGHT;
+        text(canvas, Gene::KINDS[kind], PADDING, y, TEXT_COLOR)?;
+        bar(
+            canvas,
+            Rect::new(
+                bar_left,
+                y,
+                (inner_width + PADDING - bar_left) as u32,
+                CHAR_WIDTH as u32,
+            ),
+            *frequency as f32,
+            qualitative(kind, Gene::VARIANT_COUNT),
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use std::{
+    any::Any,
+    io,
+    sync::mpsc::{
+        self, Receiver, RecvTimeoutError, Sender, SyncSender, TryRecvError, TrySendError,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    client::{
+        clock::{ClockState, SimClock},
+        keys::Command,
+    },
+    world::World,
+};
+
+type Edit = Box<dyn FnOnce(&mut World) + Send>;
+
+enum SimCommand {
+    Clock(Command),
+    Edit(Edit),
+    Stop,
+}
+
+/// The simulation thread stopped on its own, see `SimThread::latest`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimStopped;
+
+/// State published by the simulation thread
+pub struct SimFrame {
+    /// Read-only copy, see `World::view`
+    pub world: World,
+    pub clock: ClockState,
+}
+
+/// Runs the world on its own thread, so a slow update never freezes the
+/// client.
+///
+/// A `SimFrame` is published at most once per frame of the clock, when the
+/// client has taken the previous one. The commands of the clock and the
+/// edits of the world go back through another channel.
+pub struct SimThread {
+    commands: Sender<SimCommand>,
+    frames: Receiver<SimFrame>,
+    handle: Option<JoinHandle<World>>,
+}
+
+impl SimThread {
+    /// The observers of `world` run on the new thread
+    pub fn spawn(world: World, clock: SimClock) -> io::Result<Self> {
+        let (commands, commands_rx) = mpsc::channel();
+        let (frames_tx, frames) = mpsc::sync_channel(1);
+        let handle = thread::Builder::new()
+            .name("simulation".to_string())
+            .spawn(move || simulate(world, clock, commands_rx, frames_tx))?;
+
+        Ok(Self {
+            commands,
+            frames,
+            handle: Some(handle),
+        })
+    }
+
+    /// Applies a command of the clock, the others are ignored
+    pub fn command(&self, command: Command) {
+        let _ = self.commands.send(SimCommand::Clock(command));
+    }
+
+    /// Runs `f` between two updates
+    pub fn edit<F: FnOnce(&mut World) + Send + 'static>(&self, f: F) {
+        let _ = self.commands.send(SimCommand::Edit(Box::new(f)));
+    }
+
+    /// The frame published since the last call, if any. `SimStopped` once
+    /// the thread stopped without `stop`, call it to get the panic
+    pub fn latest(&self) -> Result<Option<SimFrame>, SimStopped> {
+        let mut latest = None;
+        loop {
+            match self.frames.try_recv() {
+                Ok(frame) => latest = Some(frame),
+                Err(TryRecvError::Empty) => return Ok(latest),
+                Err(TryRecvError::Disconnected) => return Err(SimStopped),
+            }
+        }
+    }
+
+    /// Waits for the thread and returns the world with its observers, or
+    /// the panic of the thread, to resume once the outputs are saved
+    pub fn stop(mut self) -> Result<World, Box<dyn Any + Send>> {
+        let _ = self.commands.send(SimCommand::Stop);
+        match self.handle.take().unwrap().join() {
+            Ok(world) => Ok(world),
+            Err(panic) => {
+                // the message may have been printed on a screen gone since
+                let msg = panic
+                    .downcast_ref::<&str>()
+                    .copied()
+                    .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+                    .unwrap_or("unknown error");
+                eprintln!("the simulation stopped: {}", msg);
+                Err(panic)
+            }
+        }
+    }
+}
+
+impl Drop for SimThread {
+    fn drop(&mut self) {
+        let _ = self.commands.send(SimCommand::Stop);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn simulate(
+    mut world: World,
+    mut clock: SimClock,
+    commands: Receiver<SimCommand>,
+    frames: SyncSender<SimFrame>,
+) -> World {
+    // the client has to see the edits made while paused
+    let mut changed = true;
+    loop {
+        loop {
+            match commands.try_recv() {
+                Ok(command) => match apply(command, &mut world, &mut clock) {
+                    Some(applied) => changed |= applied,
+                    None => return world,
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return world,
+            }
+        }
+
+        changed |= clock.run_due(|| world.update()) > 0;
+
+        if clock.is_frame_due() {
+            if changed {
+                let frame = SimFrame {
+                    world: world.view(),
+                    clock: clock.state(),
+                };
+                match frames.try_send(frame) {
+                    Ok(()) => changed = false,
+                    // the client is slower, the next frame is published instead
+                    Err(TrySendError::Full(_)) => {}
+                    Err(TrySendError::Disconnected(_)) => return world,
+                }
+            }
+            clock.frame_rendered();
+        }
+
+        // wakes up on a command
+        if let Some(wait) = clock.wait_time() {
+            match commands.recv_timeout(wait) {
+                Ok(command) => match apply(command, &mut world, &mut clock) {
+                    Some(applied) => changed |= applied,
+                    None => return world,
+                },
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return world,
+            }
+        }
+    }
+}
+
+/// Whether the published state changed, `None` to stop
+fn apply(command: SimCommand, world: &mut World, clock: &mut SimClock) -> Option<bool> {
+    match command {
+        SimCommand::Clock(command) => Some(command.apply_to_clock(clock)),
+        SimCommand::Edit(edit) => {
+            edit(world);
+            Some(true)
+        }
+        SimCommand::Stop => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{math::Position, testing};
+
+    fn paused() -> SimClock {
+        let mut clock = SimClock::new();
+        clock.toggle_pause();
+        clock
+    }
+
+    #[test]
+    fn stop_returns_the_edited_world() {
+        let world = World::new(testing::config(3));
+        let center = Position::new(world.width() / 2, world.height() / 2);
+        let sim = SimThread::spawn(world, paused()).unwrap();
+        sim.edit(|world| {
+            world.spawn_founder();
+        });
+        let world = sim.stop().unwrap();
+        assert!(world.cell(center).is_some());
+    }
+
+    #[test]
+    fn stop_returns_the_panic() {
+        let sim = SimThread::spawn(World::new(testing::config(3)), paused()).unwrap();
+        sim.edit(|_| panic!("broken edit"));
+        let Err(panic) = sim.stop() else {
+            panic!("the panic of the edit was lost");
+        };
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"broken edit"));
+    }
+}
@@ -0,0 +1,495 @@
+use std::{
+    any::Any,
+    io::{self, Write},
+    time::Duration,
+};
+
+use crossterm::{
+    QueueableCommand, cursor,
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+        MouseEventKind,
+    },
+    style::{self, Color, Print},
+    terminal::{self, ClearType},
+};
+
+use crate::{
+    client::{
+        camera::Camera,
+        clock::{ClockState, SimClock},
+        hud::Hud,
+        keys::{self, Command},
+        sim::{SimStopped, SimThread},
+        traits::{App, ClientError, EventHandler},
+    },
+    render::{BACKGROUND, ModRender, Rgb, WALL},
+    world::World,
+};
+
+/// Rows below the image, for the status line
+const STATUS_ROWS: u16 = 1;
+/// Zoom factor of one step of the mouse wheel
+const ZOOM_STEP: f32 = 1.25;
+/// Upper half block, the foreground is the top pixel and the background the bottom one
+const HALF_BLOCK: char = '\u{2580}';
+
+/// Draws the world in a terminal with 24-bit colours, two pixels per
+/// character, so it runs over SSH on the machines without a display.
+///
+/// Takes the speed and render-mode keys of `AppSdl`; Q or Ctrl+C quits.
+/// Escape does not, as in `AppSdl` it only clears the selection.
+/// The mouse wheel zooms and a drag pans.
+pub struct AppTerm {
+    /// The latest copy published by the simulation thread while it runs
+    world: World,
+    sim: Option<SimThread>,
+    sim_clock: ClockState,
+    /// The simulation thread stopped on its own, the loop is stopped
+    sim_stopped: bool,
+    /// Resumed by the caller of `run` once the outputs are saved
+    sim_panic: Option<Box<dyn Any + Send>>,
+    /// Given to the simulation thread by `init`
+    sim_scheduler: Option<SimClock>,
+    camera: Camera,
+    hud: Hud,
+    mod_render: ModRender,
+    /// Terminal size in characters
+    size: (u16, u16),
+    /// Colours drawn by the last frame, one pair per character, empty to redraw everything
+    screen: Vec<(Rgb, Rgb)>,
+    status: String,
+    /// Last position of a drag
+    drag: Option<(u16, u16)>,
+    /// Raw mode and the alternate screen are on
+    active: bool,
+    /// Failed write to the terminal, e.g. a closed SSH session, stops the loop
+    error: Option<io::Error>,
+}
+
+impl AppTerm {
+    /// The terminal is restored by `event_handler` and on drop, call it when
+    /// the loop is left another way
+    pub fn restore(&mut self) -> io::Result<()> {
+        if !self.active {
+            return Ok(());
+        }
+        self.active = false;
+
+        let mut stdout = io::stdout();
+        stdout
+            .queue(DisableMouseCapture)?
+            .queue(style::ResetColor)?
+            .queue(cursor::Show)?
+            .queue(terminal::LeaveAlternateScreen)?
+            .flush()?;
+        terminal::disable_raw_mode()
+    }
+
+    fn enter(&mut self) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        self.active = true;
+
+        let mut stdout = io::stdout();
+        stdout
+            .queue(terminal::EnterAlternateScreen)?
+            .queue(cursor::Hide)?
+            .queue(EnableMouseCapture)?
+            .queue(terminal::Clear(ClearType::All))?
+            .flush()?;
+
+        let (columns, rows) = terminal::size()?;
+        self.resize(columns, rows);
+        self.camera.fit(self.world.width(), self.world.height());
+        Ok(())
+    }
+
+    fn resize(&mut self, columns: u16, rows: u16) {
+        self.size = (columns, rows);
+        let height = rows.saturating_sub(STATUS_ROWS) as u32 * 2;
+        self.camera.set_viewport(columns as u32, height);
+        self.screen.clear();
+        self.status.clear();
+    }
+
+    fn color_at(&self, x: u16, y: u32) -> Rgb {
+        // the centre of the pixel
+        let pos = self.camera.to_world(x as f32 + 0.5, y as f32 + 0.5);
+        if let Some(cell) = self.world.get(pos) {
+            self.mod_render.color(cell, &self.world.config().cell)
+        } else if self.world.is_wall(pos) {
+            WALL
+        } else {
+            BACKGROUND
+        }
+    }
+
+    /// Writes the characters changed since the last frame
+    fn draw(&mut self) -> io::Result<()> {
+        let (columns, rows) = self.size;
+        let image_rows = rows.saturating_sub(STATUS_ROWS);
+        let redraw = self.screen.len() != columns as usize * image_rows as usize;
+        if redraw {
+            self.screen = vec![(BACKGROUND, BACKGROUND); columns as usize * image_rows as usize];
+        }
+
+        let mut out = Vec::new();
+        if redraw {
+            out.queue(style::ResetColor)?
+                .queue(terminal::Clear(ClearType::All))?;
+        }
+        // the colours and the cursor are only set when they change
+        let mut colors = None;
+        let mut cursor = None;
+        for row in 0..image_rows {
+            for column in 0..columns {
+                let pair = (
+                    self.color_at(column, row as u32 * 2),
+                    self.color_at(column, row as u32 * 2 + 1),
+                );
+                let index = row as usize * columns as usize + column as usize;
+                if !redraw && self.screen[index] == pair {
+                    continue;
+                }
+                self.screen[index] = pair;
+
+                if cursor != Some((column, row)) {
+                    out.queue(cursor::MoveTo(column, row))?;
+                }
+                if colors != Some(pair) {
+                    out.queue(style::SetForegroundColor(rgb(pair.0)))?
+                        .queue(style::SetBackgroundColor(rgb(pair.1)))?;
+                    colors = Some(pair);
+                }
+                out.queue(Print(HALF_BLOCK))?;
+                cursor = Some((column + 1, row));
+            }
+        }
+
+        let status = if self.hud.is_visible() {
+            format!(
+                "tick {}  tps {:.1}  {}  cells {}  mode {}",
+                self.world.tick(),
+                self.hud.tps(),
+                self.sim_clock,
+                self.world.count_cells(),
+                self.mod_render
+            )
+        } else {
+            String::new()
+        };
+        if status != self.status && rows > 0 {
+            let line: String = status.chars().take(columns as usize).collect();
+            out.queue(style::ResetColor)?
+                .queue(cursor::MoveTo(0, rows - 1))?
+                .queue(terminal::Clear(ClearType::CurrentLine))?
+                .queue(Print(line))?;
+            self.status = status;
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(&out)?;
+        stdout.flush()
+    }
+
+    fn command(&mut self, command: Command) {
+        if command.is_clock() {
+            if let Some(sim) = &self.sim {
+                sim.command(command);
+            }
+            return;
+        }
+        command.apply_to_view(
+            &mut self.mod_render,
+            &mut self.hud,
+            &mut self.camera,
+            &self.world,
+        );
+    }
+
+    /// true - quit
+    fn handle(&mut self, event: Event) -> bool {
+        match event {
+            Event::Key(key) if key.kind != KeyEventKind::Release => {
+                let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                match key.code {
+                    KeyCode::Char('c') if ctrl => return true,
+                    KeyCode::Char('q') => return true,
+                    code => {
+                        let shift = key.modifiers.contains(KeyModifiers::SHIFT);
+                        if let Some(command) =
+                            key_name(code).and_then(|name| keys::command(&name, shift))
+                        {
+                            self.command(command);
+                        }
+                    }
+                }
+            }
+            Event::Mouse(mouse) => {
+                // a character is one pixel wide and two high
+                let (x, y) = (mouse.column as f32 + 0.5, mouse.row as f32 * 2.0 + 1.0);
+                match mouse.kind {
+                    MouseEventKind::ScrollUp => self.camera.zoom_at(ZOOM_STEP, x, y),
+                    MouseEventKind::ScrollDown => self.camera.zoom_at(1.0 / ZOOM_STEP, x, y),
+                    // some terminals report every drag as the left button
+                    MouseEventKind::Drag(_) => {
+                        if let Some((column, row)) = self.drag {
+                            self.camera.pan(
+                                mouse.column as f32 - column as f32,
+                                (mouse.row as f32 - row as f32) * 2.0,
+                            );
+                        }
+                        self.drag = Some((mouse.column, mouse.row));
+                    }
+                    MouseEventKind::Up(_) => self.drag = None,
+                    _ => {}
+                }
+            }
+            Event::Resize(columns, rows) => self.resize(columns, rows),
+            _ => {}
+        }
+        false
+    }
+}
+
+impl App for AppTerm {
+    fn with_world(world: World) -> Self {
+        Self {
+            world,
+            sim: None,
+            sim_clock: SimClock::new().state(),
+            sim_scheduler: None,
+            sim_stopped: false,
+            sim_panic: None,
+            camera: Camera::new((80, 46), 1.0),
+            hud: Hud::new(),
+            mod_render: ModRender::Default,
+            size: (80, 24),
+            screen: Vec::new(),
+            status: String::new(),
+            drag: None,
+            active: false,
+            error: None,
+        }
+    }
+
+    fn with_sim_clock(mut self, clock: SimClock) -> Self {
+        self.sim_clock = clock.state();
+        self.sim_scheduler = Some(clock);
+        self
+    }
+
+    fn init(mut self) -> Result<Self, ClientError> {
+        if let Err(e) = self.enter() {
+            let _ = self.restore();
+            return Err(e.into());
+        }
+
+        let view = self.world.view();
+        let world = std::mem::replace(&mut self.world, view);
+        match SimThread::spawn(world, self.sim_scheduler.take().unwrap_or_default()) {
+            Ok(sim) => self.sim = Some(sim),
+            Err(e) => {
+                let _ = self.restore();
+                return Err(e.into());
+            }
+        }
+
+        Ok(self)
+    }
+
+    #[inline(always)]
+    fn world(&self) -> &World {
+        &self.world
+    }
+
+    fn render(&mut self) {
+        match self.sim.as_ref().map(SimThread::latest) {
+            Some(Ok(Some(frame))) => {
+                self.world = frame.world;
+                self.sim_clock = frame.clock;
+            }
+            Some(Err(SimStopped)) => self.sim_stopped = true,
+            _ => {}
+        }
+        self.hud.sample(&self.world);
+        if self.error.is_none()
+            && let Err(e) = self.draw()
+        {
+            self.error = Some(e);
+        }
+    }
+
+    fn take_sim_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+        self.sim_panic.take()
+    }
+}
+
+impl EventHandler for AppTerm {
+    fn event_handler(&mut self) -> bool {
+        let mut quit = self.error.is_some() || self.sim_stopped;
+        while !quit {
+            match event::poll(Duration::ZERO).and_then(|ready| {
+                if ready {
+                    event::read().map(Some)
+                } else {
+                    Ok(None)
+                }
+            }) {
+                Ok(Some(event)) => quit = self.handle(event),
+                Ok(None) => break,
+                Err(e) => {
+                    self.error = Some(e);
+                    quit = true;
+                }
+            }
+        }
+
+        if quit {
+            // before `stop`, its panic is printed on the normal screen
+            let _ = self.restore();
+            if let Some(e) = self.error.take() {
+                eprintln!("terminal error: {}", e);
+            }
+            // the observers and the final checkpoint need the real world
+            if let Some(sim) = self.sim.take() {
+                match sim.stop() {
+                    Ok(world) => self.world = world,
+                    // the last published copy is saved instead
+                    Err(panic) => self.sim_panic = Some(panic),
+                }
+            }
+        }
+        quit
+    }
+}
+
+impl Drop for AppTerm {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
+
+#[inline(always)]
+fn rgb(color: Rgb) -> Color {
+    Color::Rgb {
+        r: color.0,
+        g: color.1,
+        b: color.2,
+    }
+}
+
+/// Named like the SDL keys, see `keys::command`
+fn key_name(code: KeyCode) -> Option<String> {
+    match code {
+        KeyCode::Char(' ') => Some("Space".to_string()),
+        KeyCode::Char(c) => Some(c.to_ascii_uppercase().to_string()),
+        KeyCode::Right => Some("Right".to_string()),
+        KeyCode::Tab => Some("Tab".to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::{KeyEvent, MouseEvent};
+
+    use super::*;
+    use crate::{math::Position, pos, testing};
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Event {
+        Event::Key(KeyEvent::new(code, modifiers))
+    }
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> Event {
+        Event::Mouse(MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        })
+    }
+
+    fn app() -> AppTerm {
+        let mut world = World::new(testing::config(23));
+        let cell = world.new_cell();
+        world.spawn(pos!(10, 10), cell);
+        world.set_wall(pos!(12, 10), true);
+        world.update();
+        let mut app = AppTerm::with_world(world);
+        app.resize(80, 25);
+        app
+    }
+
+    #[test]
+    fn keys_are_named_like_sdl() {
+        assert_eq!(key_name(KeyCode::Char('e')).as_deref(), Some("E"));
+        assert_eq!(key_name(KeyCode::Char(' ')).as_deref(), Some("Space"));
+        assert_eq!(key_name(KeyCode::Char('=')).as_deref(), Some("="));
+        assert_eq!(key_name(KeyCode::Right).as_deref(), Some("Right"));
+        assert_eq!(key_name(KeyCode::Esc), None);
+    }
+
+    #[test]
+    fn quit_keys() {
+        let mut app = app();
+        assert!(app.handle(key(KeyCode::Char('q'), KeyModifiers::NONE)));
+        assert!(app.handle(key(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+        assert!(!app.handle(key(KeyCode::Esc, KeyModifiers::NONE)));
+        assert!(!app.handle(key(KeyCode::Char('c'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn keys_change_the_view() {
+        let mut app = app();
+        app.handle(key(KeyCode::Char('e'), KeyModifiers::NONE));
+        assert_eq!(app.mod_render, ModRender::Energy);
+        app.handle(key(KeyCode::Tab, KeyModifiers::NONE));
+        assert_eq!(app.mod_render, ModRender::Toxin);
+        app.handle(key(KeyCode::Char('o'), KeyModifiers::NONE));
+        assert!(!app.hud.is_visible());
+    }
+
+    #[test]
+    fn characters_are_two_pixels_high() {
+        let mut app = app();
+        // the status line is not a part of the image
+        assert_eq!(app.camera.viewport(), (80, 48));
+
+        app.camera = Camera::new(app.camera.viewport(), 1.0);
+        let cell = app.world.get(pos!(10, 10)).unwrap();
+        assert_eq!(app.color_at(10, 10), cell.color);
+        assert_eq!(app.color_at(12, 10), WALL);
+        assert_eq!(app.color_at(11, 10), BACKGROUND);
+
+        let zoom = app.camera.zoom();
+        app.handle(mouse(MouseEventKind::ScrollUp, 10, 5));
+        assert_eq!(app.camera.zoom(), zoom * ZOOM_STEP);
+        // the cell under the cursor stays in place
+        assert_eq!(app.camera.to_world(10.5, 11.0), pos!(10, 11));
+
+        app.handle(Event::Resize(40, 11));
+        assert_eq!(app.size, (40, 11));
+        assert_eq!(app.camera.viewport(), (40, 20));
+    }
+
+    #[test]
+    fn drag_pans_by_characters() {
+        let mut app = app();
+        let before = app.camera.to_screen(pos!(0, 0));
+        app.handle(mouse(
+            MouseEventKind::Drag(event::MouseButton::Left),
+            10,
+            10,
+        ));
+        app.handle(mouse(
+            MouseEventKind::Drag(event::MouseButton::Left),
+            13,
+            12,
+        ));
+        app.handle(mouse(MouseEventKind::Up(event::MouseButton::Left), 13, 12));
+        let after = app.camera.to_screen(pos!(0, 0));
+        assert_eq!((after.0 - before.0, after.1 - before.1), (3.0, 4.0));
+        assert!(app.drag.is_none());
+    }
+}
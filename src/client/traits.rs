@@ -1,19 +1,99 @@
+use std::{any::Any, fmt, io, thread, time::Instant};
+
+use crate::{
+    client::clock::{FRAME_TIME, SimClock},
+    config::{ConfigError, SimConfig},
+    world::World,
+};
+
+#[derive(Debug)]
+pub enum ClientError {
+    Config(ConfigError),
+    Io(io::Error),
+    /// Failure of the window system, e.g. no display
+    Backend(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Config(e) => write!(f, "{}", e),
+            ClientError::Io(e) => write!(f, "i/o error: {}", e),
+            ClientError::Backend(msg) => write!(f, "failed to start the client: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Config(e) => Some(e),
+            ClientError::Io(e) => Some(e),
+            ClientError::Backend(_) => None,
+        }
+    }
+}
+
+impl From<ConfigError> for ClientError {
+    fn from(e: ConfigError) -> Self {
+        ClientError::Config(e)
+    }
+}
+
+impl From<io::Error> for ClientError {
+    fn from(e: io::Error) -> Self {
+        ClientError::Io(e)
+    }
+}
+
 pub trait App: EventHandler {
-    fn new() -> Self;
-    fn init(self) -> Self;
-    fn update(&mut self);
+    /// Starts a new run from a founder at the centre of the dish
+    fn new(config: SimConfig) -> Result<Self, ClientError>
+    where
+        Self: Sized,
+    {
+        config.validate()?;
+        let mut world = World::new(config);
+        world.spawn_founder();
+        Ok(Self::with_world(world))
+    }
+    /// Continues `world`, e.g. one restored from a checkpoint
+    fn with_world(world: World) -> Self;
+    /// Replaces the default scheduler of the simulation
+    fn with_sim_clock(self, clock: SimClock) -> Self
+    where
+        Self: Sized;
+    /// Opens the window or the terminal
+    fn init(self) -> Result<Self, ClientError>
+    where
+        Self: Sized;
+    fn world(&self) -> &World;
     fn render(&mut self);
-    fn run(mut self)
+    /// Panic of the simulation thread, to resume once the outputs are saved
+    fn take_sim_panic(&mut self) -> Option<Box<dyn Any + Send>> {
+        None
+    }
+    /// Renders a frame every `FRAME_TIME`, the missed ones are skipped.
+    /// Returns the app once the loop is stopped by `event_handler`
+    fn run(mut self) -> Self
     where
         Self: Sized,
     {
+        let mut next_frame = Instant::now();
         loop {
             if self.event_handler() {
                 break;
             }
-            self.update();
             self.render();
+
+            let now = Instant::now();
+            next_frame += FRAME_TIME;
+            if next_frame < now {
+                next_frame = now + FRAME_TIME;
+            }
+            thread::sleep(next_frame - now);
         }
+        self
     }
 }
 
@@ -0,0 +1,88 @@
+use crate::render::Rgb;
+
+/// Perceptually uniform colour maps for the values in `0..=1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Colormap {
+    Viridis,
+    Inferno,
+}
+
+impl Colormap {
+    /// `t` is clamped to `0..=1`
+    pub fn sample(&self, t: f32) -> Rgb {
+        // polynomial fits of the matplotlib maps, sixth degree
+        let coefficients = match self {
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Inferno => &INFERNO,
+        };
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let channel = |i: usize| {
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(0.0, |value, c| value * t + c[i]);
+            (value.clamp(0.0, 1.0) * 255.0).round() as u8
+        };
+        (channel(0), channel(1), channel(2))
+    }
+}
+
+const VIRIDIS: [[f32; 3]; 7] = [
+    [0.277_727_33, 0.005_407_344_5, 0.334_099_8],
+    [0.105_093_04, 1.404_613_5, 1.384_590_2],
+    [-0.330_861_83, 0.214_847_56, 0.095_095_16],
+    [-4.634_230_5, -5.799_101, -19.332_441],
+    [6.228_27, 14.179_933, 56.690_55],
+    [4.776_385, -13.745_145, -65.353_03],
+    [-5.435_456, 4.645_852_6, 26.312_435],
+];
+
+const INFERNO: [[f32; 3]; 7] = [
+    [0.000_218_940_37, 0.001_651_004_6, -0.019_480_899],
+    [0.106_513_42, 0.563_956_44, 3.932_712_4],
+    [11.602_493, -3.972_854, -15.942_394],
+    [-41.703_995, 17.436_398, 44.354_145],
+    [77.162_94, -33.402_36, -81.807_31],
+    [-71.319_43, 32.626_064, 73.209_52],
+    [25.131_126, -12.242_669, -23.070_325],
+];
+
+/// Colour of a category, neighbouring keys get distant hues of the same
+/// perceived lightness
+pub fn categorical(key: u64) -> Rgb {
+    // Fibonacci hashing spreads the consecutive keys around the hue circle
+    let mixed = key.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    let hue = (mixed >> 40) as f32 / (1u64 << 24) as f32;
+    let lightness = if mixed & (1 << 39) == 0 { 0.68 } else { 0.80 };
+    oklch(lightness, 0.13, hue)
+}
+
+/// Colour `index` of `count` evenly spaced hues
+pub fn qualitative(index: usize, count: usize) -> Rgb {
+    oklch(0.72, 0.14, index as f32 / count.max(1) as f32)
+}
+
+/// `hue` in turns, converted through Oklab to sRGB
+fn oklch(lightness: f32, chroma: f32, hue: f32) -> Rgb {
+    let angle = hue * std::f32::consts::TAU;
+    let (a, b) = (chroma * angle.cos(), chroma * angle.sin());
+
+    let l = (lightness + 0.396_337_78 * a + 0.215_803_76 * b).powi(3);
+    let m = (lightness - 0.105_561_346 * a - 0.063_854_17 * b).powi(3);
+    let s = (lightness - 0.089_484_18 * a - 1.291_485_5 * b).powi(3);
+
+    let channel = |linear: f32| {
+        let linear = linear.clamp(0.0, 1.0);
+        let value = if linear <= 0.003_130_8 {
+            12.92 * linear
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+        (value * 255.0).round() as u8
+    };
+    (
+        channel(4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s),
+        channel(-1.268_438 * l + 2.609_757_4 * m - 0.341_319_38 * s),
+        channel(-0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s),
+    )
+}
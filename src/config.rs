@@ -0,0 +1,335 @@
+use std::{fmt, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::{
+        COUNT_GENES, MAX_AREA, MAX_COUNT_GENES, MAX_SIZE, MIN_STRIPE_HEIGHT, RADIUS_PETRI_DISH,
+        WIDTH,
+    },
+    mutation::MutationProfile,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SimConfig {
+    /// Seed of the random generator, a random one if not set
+    pub seed: Option<u64>,
+    pub count_genes: usize,
+    pub radius_petri_dish: i32,
+    pub width: i32,
+    /// Rows of the stripes updated in parallel, see `World::update`.
+    /// `0` updates the cells one by one
+    pub stripe_height: i32,
+    pub cell: CellConfig,
+    /// Initial mutation profile of the seeded cells
+    pub mutation: MutationProfile,
+}
+
+impl SimConfig {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        Self::from_toml(&text)
+    }
+
+    pub fn from_toml(text: &str) -> Result<Self, ConfigError> {
+        let config: Self = toml::from_str(text).map_err(ConfigError::Parse)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).expect("SimConfig is always serializable")
+    }
+
+    #[inline(always)]
+    pub fn height(&self) -> i32 {
+        self.radius_petri_dish * 2
+    }
+
+    /// Positions of the dish, `0` if the config is invalid
+    pub fn area(&self) -> usize {
+        let (width, height) = (self.width.max(0) as usize, self.height().max(0) as usize);
+        width.checked_mul(height).unwrap_or(0)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        check(
+            (1..=MAX_COUNT_GENES).contains(&self.count_genes),
+            format!("count_genes must be in 1..={}", MAX_COUNT_GENES),
+        )?;
+        check(
+            (1..=MAX_SIZE / 2).contains(&self.radius_petri_dish),
+            format!("radius_petri_dish must be in 1..={}", MAX_SIZE / 2),
+        )?;
+        check(
+            (2..=MAX_SIZE).contains(&self.width),
+            format!("width must be in 2..={}", MAX_SIZE),
+        )?;
+        check(
+            self.area() <= MAX_AREA,
+            format!("width * radius_petri_dish * 2 must be at most {}", MAX_AREA),
+        )?;
+        check(
+            self.stripe_height == 0 || self.stripe_height >= MIN_STRIPE_HEIGHT,
+            format!("stripe_height must be 0 or at least {}", MIN_STRIPE_HEIGHT),
+        )?;
+        self.cell.validate()?;
+        self.mutation.validate()
+    }
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            count_genes: COUNT_GENES,
+            radius_petri_dish: RADIUS_PETRI_DISH,
+            width: WIDTH,
+            stripe_height: 0,
+            cell: CellConfig::default(),
+            mutation: MutationProfile::default(),
+        }
+    }
+}
+
+/// Constants of the cell metabolism.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CellConfig {
+    pub initial_energy: f32,
+    pub initial_health: f32,
+    pub initial_max_lifetime: u32,
+    /// Energy above which a cell is able to divide
+    pub reproduction_threshold: f32,
+    /// Energy synthesis: `synthesis_energy / (energy * synthesis_divisor)`
+    pub synthesis_energy: f32,
+    pub synthesis_divisor: f32,
+    /// Energy spent to synthesize toxin or health
+    pub synthesis_cost: f32,
+    /// Energy spent per tick: `metabolism * max_lifetime * health * depth / 2`
+    pub metabolism: f32,
+    /// Damage per unit of attacker health
+    pub attack_factor: f32,
+    /// A cell is alive while `min_energy <= energy < max_energy`
+    pub min_energy: f32,
+    pub max_energy: f32,
+}
+
+impl CellConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let values = [
+            self.initial_energy,
+            self.initial_health,
+            self.reproduction_threshold,
+            self.synthesis_energy,
+            self.synthesis_divisor,
+            self.synthesis_cost,
+            self.metabolism,
+            self.attack_factor,
+            self.min_energy,
+            self.max_energy,
+        ];
+        check(
+            values.iter().all(|value| value.is_finite()),
+            "cell parameters must be finite",
+        )?;
+        check(
+            self.initial_energy > 0.0,
+            "cell.initial_energy must be positive",
+        )?;
+        check(
+            self.initial_health > 0.0,
+            "cell.initial_health must be positive",
+        )?;
+        check(
+            self.reproduction_threshold > 0.0,
+            "cell.reproduction_threshold must be positive",
+        )?;
+        check(
+            self.synthesis_divisor > 0.0,
+            "cell.synthesis_divisor must be positive",
+        )?;
+        check(
+            self.synthesis_cost >= 0.0,
+            "cell.synthesis_cost must not be negative",
+        )?;
+        check(
+            self.metabolism >= 0.0,
+            "cell.metabolism must not be negative",
+        )?;
+        check(
+            self.attack_factor >= 0.0,
+            "cell.attack_factor must not be negative",
+        )?;
+        check(
+            self.min_energy < self.max_energy,
+            "cell.min_energy must be less than cell.max_energy",
+        )
+    }
+}
+
+impl Default for CellConfig {
+    fn default() -> Self {
+        Self {
+            initial_energy: 10.0,
+            initial_health: 1.0,
+            initial_max_lifetime: 16,
+            reproduction_threshold: 2.5,
+            synthesis_energy: 5.0,
+            synthesis_divisor: 2.25,
+            synthesis_cost: 1.0,
+            metabolism: 0.003,
+            attack_factor: 3.0,
+            min_energy: 1.3,
+            max_energy: 1000.0,
+        }
+    }
+}
+
+#[inline(always)]
+pub(crate) fn check<S: Into<String>>(condition: bool, msg: S) -> Result<(), ConfigError> {
+    if condition {
+        Ok(())
+    } else {
+        Err(ConfigError::Invalid(msg.into()))
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "failed to read config: {}", e),
+            ConfigError::Parse(e) => write!(f, "failed to parse config: {}", e),
+            ConfigError::Invalid(msg) => write!(f, "invalid config: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io(e) => Some(e),
+            ConfigError::Parse(e) => Some(e),
+            ConfigError::Invalid(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn invalid(config: SimConfig) -> String {
+        match config.validate() {
+            Err(ConfigError::Invalid(msg)) => msg,
+            other => panic!("expected an invalid config, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn default_is_valid() {
+        SimConfig::default().validate().unwrap();
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let config = SimConfig {
+            seed: Some(7),
+            stripe_height: 16,
+            ..SimConfig::default()
+        };
+        assert_eq!(SimConfig::from_toml(&config.to_toml()).unwrap(), config);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        assert!(matches!(
+            SimConfig::from_toml("widht = 100"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn messages_name_the_limits() {
+        let msg = invalid(SimConfig {
+            count_genes: MAX_COUNT_GENES + 1,
+            ..SimConfig::default()
+        });
+        assert!(msg.contains(&MAX_COUNT_GENES.to_string()), "{}", msg);
+
+        let msg = invalid(SimConfig {
+            stripe_height: MIN_STRIPE_HEIGHT - 1,
+            ..SimConfig::default()
+        });
+        assert!(msg.contains(&MIN_STRIPE_HEIGHT.to_string()), "{}", msg);
+
+        let msg = invalid(SimConfig {
+            width: MAX_SIZE + 1,
+            ..SimConfig::default()
+        });
+        assert!(msg.contains(&MAX_SIZE.to_string()), "{}", msg);
+    }
+
+    #[test]
+    fn sizes_are_bounded() {
+        for config in [
+            SimConfig {
+                radius_petri_dish: 0,
+                ..SimConfig::default()
+            },
+            SimConfig {
+                radius_petri_dish: MAX_SIZE / 2 + 1,
+                ..SimConfig::default()
+            },
+            SimConfig {
+                width: 1,
+                ..SimConfig::default()
+            },
+        ] {
+            invalid(config);
+        }
+
+        // both sides in bounds, too many positions
+        let msg = invalid(SimConfig {
+            radius_petri_dish: MAX_SIZE / 2,
+            width: MAX_SIZE,
+            ..SimConfig::default()
+        });
+        assert!(msg.contains(&MAX_AREA.to_string()), "{}", msg);
+
+        let widest = SimConfig {
+            radius_petri_dish: (MAX_AREA / MAX_SIZE as usize / 2) as i32,
+            width: MAX_SIZE,
+            ..SimConfig::default()
+        };
+        widest.validate().unwrap();
+        assert_eq!(widest.area(), MAX_AREA);
+    }
+
+    #[test]
+    fn cell_and_mutation_are_validated() {
+        let mut config = SimConfig::default();
+        config.cell.min_energy = config.cell.max_energy;
+        invalid(config);
+
+        let mut config = SimConfig::default();
+        config.cell.metabolism = f32::NAN;
+        invalid(config);
+
+        let mut config = SimConfig::default();
+        config.cell.max_energy = f32::INFINITY;
+        invalid(config);
+
+        let mut config = SimConfig::default();
+        config.mutation.cell = 1.5;
+        invalid(config);
+    }
+}
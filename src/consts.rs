@@ -1,3 +1,24 @@
 pub const PROBABILITY_OF_MUTATION: f64 = 0.001;
 pub const COUNT_GENES: usize = 32;
+pub const MAX_COUNT_GENES: usize = 128;
 pub const RADIUS_PETRI_DISH: i32 = 60;
+pub const WIDTH: i32 = 360;
+/// Upper bound of the width and the height of the dish
+pub const MAX_SIZE: i32 = 1 << 15;
+/// Upper bound of the positions of the dish, the grids are allocated upfront
+pub const MAX_AREA: usize = 1 << 24;
+/// Default `MutationProfile::rate_drift`, the mutation rate evolves
+pub const DEFAULT_RATE_DRIFT: f64 = 0.1;
+/// Upper bound of `MutationProfile::drift` and `rate_drift`, the rates are
+/// scaled by up to `e^MAX_DRIFT`
+pub const MAX_DRIFT: f64 = 10.0;
+/// Upper bound of the weights of `OperatorWeights`
+pub const MAX_OPERATOR_WEIGHT: f64 = 1e6;
+/// Ticks between the cleanups of the genotypes that died out
+pub const GENOTYPE_PRUNE_INTERVAL: u64 = 1024;
+/// Rows between the position of a cell and the farthest position it changes
+/// in an update: the fall and one step of a gene
+pub const INTERACTION_RADIUS: i32 = 2;
+/// Stripes of the same parity are updated at once, the stripe between two
+/// of them covers the rows both reach
+pub const MIN_STRIPE_HEIGHT: i32 = 2 * INTERACTION_RADIUS;
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+
+use crate::{genome::Gene, world::World};
+
+/// Genetic diversity of the living population.
+///
+/// Computed in one pass over the genomes, `O(cells * genes)`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Diversity {
+    pub population: usize,
+    /// Shannon entropy of every locus, in bits
+    pub locus_entropy: Vec<f64>,
+    /// Average Hamming distance between two distinct genomes
+    pub mean_pairwise_hamming: f64,
+    pub unique_genotypes: usize,
+    /// Share of the population carrying the most common genotype
+    pub dominant_share: f64,
+}
+
+impl Diversity {
+    pub fn measure(world: &World) -> Self {
+        let population = world.count_cells();
+        if population == 0 {
+            return Self::default();
+        }
+
+        let len = world
+            .iter()
+            .map(|(_, cell)| cell.genome.len())
+            .max()
+            .unwrap_or(0);
+        // `None` - the genome is shorter than the locus
+        let mut loci: Vec<HashMap<Option<Gene>, usize>> = vec![HashMap::new(); len];
+        let mut genotypes: HashMap<&[Gene], usize> = HashMap::new();
+
+        for (_, cell) in world.iter() {
+            let genes = cell.genome.genes();
+            for (i, locus) in loci.iter_mut().enumerate() {
+                *locus.entry(genes.get(i).copied()).or_default() += 1;
+            }
+            *genotypes.entry(genes).or_default() += 1;
+        }
+
+        let n = population as f64;
+        let locus_entropy = loci
+            .iter()
+            .map(|locus| {
+                0.0 - locus
+                    .values()
+                    .map(|&count| {
+                        let p = count as f64 / n;
+                        p * p.log2()
+                    })
+                    .sum::<f64>()
+            })
+            .collect();
+
+        let mean_pairwise_hamming = if population > 1 {
+            loci.iter()
+                .map(|locus| {
+                    let same: f64 = locus.values().map(|&c| (c * c) as f64).sum();
+                    (n * n - same) / (n * (n - 1.0))
+                })
+                .sum()
+        } else {
+            0.0
+        };
+
+        let dominant = genotypes.values().copied().max().unwrap_or(0);
+
+        Self {
+            population,
+            locus_entropy,
+            mean_pairwise_hamming,
+            unique_genotypes: genotypes.len(),
+            dominant_share: dominant as f64 / n,
+        }
+    }
+
+    pub fn mean_entropy(&self) -> f64 {
+        if self.locus_entropy.is_empty() {
+            return 0.0;
+        }
+        self.locus_entropy.iter().sum::<f64>() / self.locus_entropy.len() as f64
+    }
+
+    /// true - every living cell has the same genotype
+    pub fn is_clonal(&self) -> bool {
+        self.unique_genotypes <= 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{genome::Genome, math::Position, testing};
+
+    /// World of the cells carrying `genomes`, in a row
+    fn population(genomes: &[&[Gene]]) -> World {
+        let mut world = World::new(testing::config(1));
+        let mut snapshot = world.snapshot();
+        snapshot.cells = genomes
+            .iter()
+            .enumerate()
+            .map(|(i, genes)| {
+                let mut cell = world.new_cell();
+                cell.genome = Genome::new(genes.len());
+                for (index, gene) in genes.iter().enumerate() {
+                    cell.genome.set(index, *gene);
+                }
+                (Position::new(10 + 2 * i as i32, 20), cell)
+            })
+            .collect();
+        World::from_snapshot(snapshot).unwrap()
+    }
+
+    #[test]
+    fn entropy_and_hamming_of_a_known_population() {
+        use Gene::{None as N, Stop as S};
+        let world = population(&[&[S, N, S], &[S, N, S], &[S, S, S], &[N, N, S]]);
+        let diversity = Diversity::measure(&world);
+
+        // 3 of 4 against 1 of 4 at the first two loci, one gene at the last
+        let entropy = -(0.75 * 0.75f64.log2() + 0.25 * 0.25f64.log2());
+        assert_eq!(diversity.population, 4);
+        assert_eq!(diversity.locus_entropy.len(), 3);
+        assert!((diversity.locus_entropy[0] - entropy).abs() < 1e-12);
+        assert!((diversity.locus_entropy[1] - entropy).abs() < 1e-12);
+        assert_eq!(diversity.locus_entropy[2], 0.0);
+        assert!((diversity.mean_entropy() - 2.0 * entropy / 3.0).abs() < 1e-12);
+
+        // the distances of the 6 pairs are 0, 1, 1, 1, 1 and 2
+        assert!((diversity.mean_pairwise_hamming - 1.0).abs() < 1e-12);
+        assert_eq!(diversity.unique_genotypes, 3);
+        assert_eq!(diversity.dominant_share, 0.5);
+        assert!(!diversity.is_clonal());
+    }
+
+    #[test]
+    fn shorter_genomes_differ_at_the_missing_loci() {
+        use Gene::Stop as S;
+        let world = population(&[&[S, S], &[S]]);
+        let diversity = Diversity::measure(&world);
+        assert_eq!(diversity.locus_entropy, [0.0, 1.0]);
+        assert_eq!(diversity.mean_pairwise_hamming, 1.0);
+    }
+
+    #[test]
+    fn clones_and_empty_worlds() {
+        let genes = [Gene::Stop, Gene::None];
+        let diversity = Diversity::measure(&population(&[&genes, &genes, &genes]));
+        assert!(diversity.is_clonal());
+        assert_eq!(diversity.mean_entropy(), 0.0);
+        assert_eq!(diversity.mean_pairwise_hamming, 0.0);
+        assert_eq!(diversity.dominant_share, 1.0);
+
+        assert_eq!(Diversity::measure(&population(&[])), Diversity::default());
+    }
+}
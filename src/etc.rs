@@ -1,8 +1,40 @@
+use std::io::{self, Read, Write};
+
+use bincode::Options;
 use rand::Rng;
+use serde::{Serialize, de::DeserializeOwned};
 
-use crate::consts::PROBABILITY_OF_MUTATION;
+/// Random generator of the simulation, seeded by `World`
+pub type SimRng = rand_chacha::ChaCha8Rng;
 
 #[inline(always)]
-pub fn is_mutated(pow: f64) -> bool {
-    rand::thread_rng().gen_bool(PROBABILITY_OF_MUTATION * pow)
+pub fn is_mutated(rng: &mut SimRng, probability: f64) -> bool {
+    rng.gen_bool(probability.clamp(0.0, 1.0))
+}
+
+/// Writes `value` in the compact binary format of the replays and the checkpoints
+pub(crate) fn encode<T: Serialize, W: Write>(writer: W, value: &T) -> io::Result<()> {
+    bincode::DefaultOptions::new()
+        .serialize_into(writer, value)
+        .map_err(|e| into_io_error(*e))
+}
+
+pub(crate) fn decode<T: DeserializeOwned, R: Read>(reader: R) -> io::Result<T> {
+    bincode::DefaultOptions::new()
+        .deserialize_from(reader)
+        .map_err(|e| into_io_error(*e))
+}
+
+fn into_io_error(e: bincode::ErrorKind) -> io::Error {
+    match e {
+        bincode::ErrorKind::Io(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
 }
+
+pub const ANSI_RESET: &str = "\x1b[0m";
+pub const ANSI_DIM: &str = "\x1b[2m";
+pub const ANSI_BOLD_INVERSE: &str = "\x1b[1;7m";
+pub const ANSI_RED: &str = "\x1b[31m";
+pub const ANSI_GREEN: &str = "\x1b[32m";
+pub const ANSI_YELLOW: &str = "\x1b[33m";
@@ -0,0 +1,149 @@
+use crate::genome::{Gene, Genome};
+
+/// Static analysis of the control flow of a genome.
+///
+/// Execution always wraps to step 1 after `Gene::Stop`, so every gene after
+/// the first reachable `Stop` is dead code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenomeAnalysis {
+    pub reachable: Vec<bool>,
+    /// Reachable genes with an effect, everything except `Gene::None`
+    pub effective: usize,
+    /// Reachable `Gene::None`
+    pub nops: usize,
+    /// Dead genes
+    pub junk: usize,
+}
+
+impl GenomeAnalysis {
+    pub fn new(genome: &Genome) -> Self {
+        let reachable = genome.reachable();
+        let (mut effective, mut nops, mut junk) = (0, 0, 0);
+        for (gene, &reachable) in genome.genes().iter().zip(reachable.iter()) {
+            match (reachable, gene) {
+                (false, _) => junk += 1,
+                (true, Gene::None) => nops += 1,
+                (true, _) => effective += 1,
+            }
+        }
+
+        Self {
+            reachable,
+            effective,
+            nops,
+            junk,
+        }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.reachable.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.reachable.is_empty()
+    }
+
+    /// A point mutation of a neutral locus never changes the behaviour
+    #[inline]
+    pub fn is_neutral(&self, index: usize) -> bool {
+        !self.reachable[index]
+    }
+
+    pub fn neutral_loci(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).filter(|&index| self.is_neutral(index))
+    }
+
+    /// Share of the dead genes
+    pub fn junk_fraction(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        self.junk as f64 / self.len() as f64
+    }
+}
+
+/// Loci changed by a mutation of `parent` into `child`
+/// and how many of them were neutral in `parent`
+pub fn count_mutated_loci(parent: &Genome, child: &Genome) -> (usize, usize) {
+    let reachable = parent.reachable();
+    let mut changed = 0;
+    let mut neutral = 0;
+    for (index, (a, b)) in parent.genes().iter().zip(child.genes()).enumerate() {
+        if a != b {
+            changed += 1;
+            if !reachable[index] {
+                neutral += 1;
+            }
+        }
+    }
+
+    (changed, neutral)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::TypeSynthesis;
+
+    const SYNTHESIS: Gene = Gene::Synthesis(TypeSynthesis::Energy);
+
+    fn genome(genes: &[Gene]) -> Genome {
+        let mut genome = Genome::new(genes.len());
+        for (index, gene) in genes.iter().enumerate() {
+            genome.set(index, *gene);
+        }
+        genome
+    }
+
+    #[test]
+    fn genes_after_stop_are_dead() {
+        let analysis =
+            GenomeAnalysis::new(&genome(&[SYNTHESIS, Gene::Stop, Gene::None, SYNTHESIS]));
+        assert_eq!(analysis.reachable, [true, true, false, false]);
+        assert_eq!(
+            (analysis.effective, analysis.nops, analysis.junk),
+            (2, 0, 2)
+        );
+        assert_eq!(analysis.neutral_loci().collect::<Vec<_>>(), [2, 3]);
+        assert_eq!(analysis.junk_fraction(), 0.5);
+    }
+
+    #[test]
+    fn stop_jumps_back_to_step_one() {
+        // step 0 runs once, then the loop 1..=3 never reaches the last gene
+        let analysis = GenomeAnalysis::new(&genome(&[
+            Gene::Stop,
+            Gene::None,
+            SYNTHESIS,
+            Gene::Stop,
+            SYNTHESIS,
+        ]));
+        assert_eq!(analysis.reachable, [true, true, true, true, false]);
+        assert_eq!(
+            (analysis.effective, analysis.nops, analysis.junk),
+            (3, 1, 1)
+        );
+        assert!(analysis.is_neutral(4));
+        assert!(!analysis.is_neutral(0));
+    }
+
+    #[test]
+    fn genome_without_stop_wraps_to_zero() {
+        let analysis = GenomeAnalysis::new(&genome(&[Gene::None, SYNTHESIS, Gene::None]));
+        assert_eq!(analysis.reachable, [true; 3]);
+        assert_eq!(analysis.junk_fraction(), 0.0);
+        assert_eq!(analysis.neutral_loci().count(), 0);
+    }
+
+    #[test]
+    fn mutated_loci_are_neutral_after_stop() {
+        let parent = genome(&[SYNTHESIS, Gene::Stop, Gene::None, SYNTHESIS]);
+        let dead = genome(&[SYNTHESIS, Gene::Stop, Gene::Stop, Gene::None]);
+        assert_eq!(count_mutated_loci(&parent, &dead), (2, 2));
+        let live = genome(&[Gene::None, Gene::Stop, Gene::Stop, SYNTHESIS]);
+        assert_eq!(count_mutated_loci(&parent, &live), (2, 1));
+        assert_eq!(count_mutated_loci(&parent, &parent), (0, 0));
+    }
+}
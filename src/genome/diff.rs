@@ -0,0 +1,250 @@
+use std::fmt::{self, Write as _};
+
+use crate::{
+    etc::{ANSI_DIM, ANSI_GREEN, ANSI_RED, ANSI_RESET, ANSI_YELLOW},
+    genome::{Gene, Genome},
+};
+
+/// `old` and `new` are the loci in the compared genomes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    Equal {
+        old: usize,
+        new: usize,
+        gene: Gene,
+    },
+    Substitute {
+        old: usize,
+        new: usize,
+        from: Gene,
+        to: Gene,
+    },
+    Insert {
+        new: usize,
+        gene: Gene,
+    },
+    Delete {
+        old: usize,
+        gene: Gene,
+    },
+}
+
+impl DiffOp {
+    #[inline]
+    pub fn is_change(&self) -> bool {
+        !matches!(self, DiffOp::Equal { .. })
+    }
+}
+
+/// Alignment of two genomes with the minimal number of substitutions,
+/// insertions and deletions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenomeDiff {
+    pub ops: Vec<DiffOp>,
+}
+
+impl GenomeDiff {
+    pub fn new(old: &Genome, new: &Genome) -> Self {
+        let (a, b) = (old.genes(), new.genes());
+        let (n, m) = (a.len(), b.len());
+
+        // dist[i][j] - edit distance between a[i..] and b[j..]
+        let mut dist = vec![vec![0u32; m + 1]; n + 1];
+        for i in (0..=n).rev() {
+            for j in (0..=m).rev() {
+                dist[i][j] = if i == n {
+                    (m - j) as u32
+                } else if j == m {
+                    (n - i) as u32
+                } else {
+                    let diagonal = dist[i + 1][j + 1] + (a[i] != b[j]) as u32;
+                    diagonal.min(dist[i + 1][j] + 1).min(dist[i][j + 1] + 1)
+                };
+            }
+        }
+
+        let mut ops = Vec::with_capacity(n.max(m));
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && dist[i][j] == dist[i + 1][j + 1] + (a[i] != b[j]) as u32 {
+                ops.push(if a[i] == b[j] {
+                    DiffOp::Equal {
+                        old: i,
+                        new: j,
+                        gene: a[i],
+                    }
+                } else {
+                    DiffOp::Substitute {
+                        old: i,
+                        new: j,
+                        from: a[i],
+                        to: b[j],
+                    }
+                });
+                i += 1;
+                j += 1;
+            } else if i < n && dist[i][j] == dist[i + 1][j] + 1 {
+                ops.push(DiffOp::Delete { old: i, gene: a[i] });
+                i += 1;
+            } else {
+                ops.push(DiffOp::Insert { new: j, gene: b[j] });
+                j += 1;
+            }
+        }
+
+        Self { ops }
+    }
+
+    pub fn changes(&self) -> impl Iterator<Item = &DiffOp> {
+        self.ops.iter().filter(|op| op.is_change())
+    }
+
+    pub fn distance(&self) -> usize {
+        self.changes().count()
+    }
+
+    pub fn is_identical(&self) -> bool {
+        self.distance() == 0
+    }
+
+    /// Removed genes are red, added are green, substituted are yellow
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for op in &self.ops {
+            let color = match op {
+                DiffOp::Equal { .. } => ANSI_DIM,
+                DiffOp::Substitute { .. } => ANSI_YELLOW,
+                DiffOp::Insert { .. } => ANSI_GREEN,
+                DiffOp::Delete { .. } => ANSI_RED,
+            };
+            let _ = writeln!(out, "{}{}{}", color, op, ANSI_RESET);
+        }
+        out
+    }
+}
+
+impl fmt::Display for DiffOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiffOp::Equal { old, new, gene } => write!(f, "  {:>3} {:>3}  {}", old, new, gene),
+            DiffOp::Substitute { old, new, from, to } => {
+                write!(f, "~ {:>3} {:>3}  {} -> {}", old, new, from, to)
+            }
+            DiffOp::Insert { new, gene } => write!(f, "+     {:>3}  {}", new, gene),
+            DiffOp::Delete { old, gene } => write!(f, "- {:>3}      {}", old, gene),
+        }
+    }
+}
+
+impl fmt::Display for GenomeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for op in &self.ops {
+            writeln!(f, "{}", op)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::{etc::SimRng, genome::TypeSynthesis, math::Direction, traits::GetRandomVariant};
+
+    const ENERGY: Gene = Gene::Synthesis(TypeSynthesis::Energy);
+    const TOXIN: Gene = Gene::Synthesis(TypeSynthesis::Toxin);
+    const DIVIDE: Gene = Gene::Reproduction(Direction::Top);
+
+    fn genome(genes: &[Gene]) -> Genome {
+        let mut genome = Genome::new(genes.len());
+        for (index, gene) in genes.iter().enumerate() {
+            genome.set(index, *gene);
+        }
+        genome
+    }
+
+    #[test]
+    fn identical_genomes() {
+        let a = genome(&[ENERGY, DIVIDE, Gene::Stop]);
+        let diff = GenomeDiff::new(&a, &a);
+        assert!(diff.is_identical());
+        assert_eq!(diff.ops.len(), 3);
+    }
+
+    #[test]
+    fn single_edits() {
+        let a = genome(&[ENERGY, DIVIDE, Gene::Stop]);
+
+        let diff = GenomeDiff::new(&a, &genome(&[ENERGY, TOXIN, Gene::Stop]));
+        assert_eq!(
+            diff.changes().copied().collect::<Vec<_>>(),
+            [DiffOp::Substitute {
+                old: 1,
+                new: 1,
+                from: DIVIDE,
+                to: TOXIN
+            }]
+        );
+
+        let b = genome(&[ENERGY, Gene::Stop]);
+        let diff = GenomeDiff::new(&a, &b);
+        assert_eq!(
+            diff.changes().copied().collect::<Vec<_>>(),
+            [DiffOp::Delete {
+                old: 1,
+                gene: DIVIDE
+            }]
+        );
+        let diff = GenomeDiff::new(&b, &a);
+        assert_eq!(
+            diff.changes().copied().collect::<Vec<_>>(),
+            [DiffOp::Insert {
+                new: 1,
+                gene: DIVIDE
+            }]
+        );
+    }
+
+    #[test]
+    fn edit_distance() {
+        // the shift by one costs two edits, not one per gene
+        let a = genome(&[ENERGY, DIVIDE, TOXIN, DIVIDE, TOXIN]);
+        let b = genome(&[DIVIDE, TOXIN, DIVIDE, TOXIN, ENERGY]);
+        assert_eq!(GenomeDiff::new(&a, &b).distance(), 2);
+    }
+
+    #[test]
+    fn ops_cover_both_genomes() {
+        let random = |rng: &mut SimRng| {
+            let len = rng.gen_range(1..12);
+            let genes: Vec<Gene> = (0..len).map(|_| Gene::None.get_rand_variant(rng)).collect();
+            genome(&genes)
+        };
+        let mut rng = SimRng::seed_from_u64(3);
+        for _ in 0..200 {
+            let (a, b) = (random(&mut rng), random(&mut rng));
+            let diff = GenomeDiff::new(&a, &b);
+            assert_eq!(diff.distance(), GenomeDiff::new(&b, &a).distance());
+            assert!(diff.distance() <= a.len().max(b.len()));
+
+            let (mut old, mut new) = (Vec::new(), Vec::new());
+            for op in &diff.ops {
+                match *op {
+                    DiffOp::Equal { gene, .. } => {
+                        old.push(gene);
+                        new.push(gene);
+                    }
+                    DiffOp::Substitute { from, to, .. } => {
+                        old.push(from);
+                        new.push(to);
+                    }
+                    DiffOp::Insert { gene, .. } => new.push(gene),
+                    DiffOp::Delete { gene, .. } => old.push(gene),
+                }
+            }
+            assert_eq!(old, a.genes());
+            assert_eq!(new, b.genes());
+        }
+    }
+}
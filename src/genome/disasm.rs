@@ -0,0 +1,79 @@
+use std::fmt::{self, Write as _};
+
+use crate::{
+    etc::{ANSI_BOLD_INVERSE, ANSI_DIM, ANSI_RESET},
+    genome::{Gene, Genome},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DisasmLine {
+    pub index: usize,
+    pub gene: Gene,
+    /// false - dead code, never executed
+    pub reachable: bool,
+    /// The gene at `Genome::step`
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disassembly {
+    pub lines: Vec<DisasmLine>,
+}
+
+impl Disassembly {
+    pub fn new(genome: &Genome) -> Self {
+        let reachable = genome.reachable();
+        let lines = genome
+            .genes()
+            .iter()
+            .enumerate()
+            .map(|(index, &gene)| DisasmLine {
+                index,
+                gene,
+                reachable: reachable[index],
+                current: index == genome.step,
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    pub fn count_reachable(&self) -> usize {
+        self.lines.iter().filter(|line| line.reachable).count()
+    }
+
+    /// Dead genes are dimmed, the current gene is highlighted
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            let style = match (line.current, line.reachable) {
+                (true, _) => ANSI_BOLD_INVERSE,
+                (false, false) => ANSI_DIM,
+                (false, true) => "",
+            };
+            let _ = writeln!(out, "{}{}{}", style, line, ANSI_RESET);
+        }
+        out
+    }
+}
+
+impl fmt::Display for DisasmLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let marker = if self.current { '>' } else { ' ' };
+        write!(f, "{}{:>3}  ", marker, self.index)?;
+        if self.reachable {
+            write!(f, "{}", self.gene)
+        } else {
+            write!(f, "{:<18}; dead", self.gene.to_string())
+        }
+    }
+}
+
+impl fmt::Display for Disassembly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,527 @@
+use std::{
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+use serde::{Deserialize, Serialize};
+use variant_count::VariantCount;
+use variantly::Variantly;
+
+use crate::{
+    consts::MAX_COUNT_GENES,
+    etc::{SimRng, is_mutated},
+    math::Direction,
+    mutation::{MutationProfile, Operator},
+    traits::{GetRandomVariant, Mutable},
+};
+
+pub mod analysis;
+pub mod diff;
+pub mod disasm;
+
+pub type GenotypeHash = u64;
+
+/// Two genomes are equal when they carry the same genes, the execution state
+/// (`step`) is not a part of the genotype.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(into = "GenomeRepr", try_from = "GenomeRepr")]
+pub struct Genome {
+    pub step: usize,
+    step_for_add: usize,
+    len: usize,
+    inner: [Gene; MAX_COUNT_GENES],
+}
+
+impl Genome {
+    /// `count_genes` must be in `1..=MAX_COUNT_GENES`
+    pub fn new(count_genes: usize) -> Self {
+        let genome = Self {
+            step: 0,
+            step_for_add: 0,
+            len: count_genes.clamp(1, MAX_COUNT_GENES),
+            inner: [Gene::default(); MAX_COUNT_GENES],
+        };
+
+        genome
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::LeftDown))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::Left))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::LeftTop))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::Top))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::RightTop))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::Right))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::RightDown))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Reproduction(Direction::Down))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Synthesis(TypeSynthesis::Energy))
+            .add_gene(Gene::Stop)
+    }
+
+    fn add_gene(mut self, gene: Gene) -> Self {
+        if self.step_for_add < self.len {
+            self.inner[self.step_for_add] = gene;
+            self.step_for_add += 1;
+        }
+        self
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline(always)]
+    pub fn genes(&self) -> &[Gene] {
+        &self.inner[..self.len]
+    }
+
+    /// FNV-1a of `Gene::code` of every gene, stable between runs
+    pub fn genotype_hash(&self) -> GenotypeHash {
+        self.genes()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, gene| {
+                (hash ^ gene.code() as u64).wrapping_mul(0x0100_0000_01b3)
+            })
+    }
+
+    #[inline]
+    pub fn get(&self) -> &Gene {
+        &self.inner[self.step]
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut Gene {
+        &mut self.inner[self.step]
+    }
+
+    pub fn set(&mut self, index: usize, value: Gene) {
+        self.inner[index] = value;
+    }
+
+    #[inline]
+    pub fn next(&mut self) {
+        self.step += 1;
+        if self.step >= self.len {
+            self.step = 0;
+        }
+    }
+
+    /// Step executed after the gene at `index`, see `Cell::update`
+    #[inline]
+    pub fn successor(&self, index: usize) -> usize {
+        let next = match self.inner[index] {
+            Gene::Stop => 1,
+            _ => index + 1,
+        };
+        if next >= self.len { 0 } else { next }
+    }
+
+    /// Genes that can be executed by a newborn, starting from step 0
+    pub fn reachable(&self) -> Vec<bool> {
+        let mut reachable = vec![false; self.len];
+        let mut step = 0;
+        while !reachable[step] {
+            reachable[step] = true;
+            step = self.successor(step);
+        }
+        reachable
+    }
+}
+
+impl PartialEq for Genome {
+    fn eq(&self, other: &Self) -> bool {
+        self.genes() == other.genes()
+    }
+}
+
+impl Eq for Genome {}
+
+impl Hash for Genome {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.genes().hash(state);
+    }
+}
+
+/// Serialized form of `Genome`, only the used genes are stored
+#[derive(Serialize, Deserialize)]
+struct GenomeRepr {
+    step: usize,
+    genes: Vec<Gene>,
+}
+
+impl From<Genome> for GenomeRepr {
+    fn from(genome: Genome) -> Self {
+        Self {
+            step: genome.step,
+            genes: genome.genes().to_vec(),
+        }
+    }
+}
+
+impl TryFrom<GenomeRepr> for Genome {
+    type Error = String;
+
+    fn try_from(repr: GenomeRepr) -> Result<Self, Self::Error> {
+        let len = repr.genes.len();
+        if !(1..=MAX_COUNT_GENES).contains(&len) {
+            return Err(format!("genome length must be in 1..={}", MAX_COUNT_GENES));
+        }
+        if repr.step >= len {
+            return Err("genome step is out of the genome".to_string());
+        }
+
+        let mut inner = [Gene::default(); MAX_COUNT_GENES];
+        inner[..len].copy_from_slice(&repr.genes);
+        Ok(Self {
+            step: repr.step,
+            step_for_add: len,
+            len,
+            inner,
+        })
+    }
+}
+
+impl Mutable for Genome {
+    fn mutate(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool {
+        self.inner[..self.len].iter_mut().for_each(|gene| {
+            gene.mutate(profile, rng);
+        });
+        self.step = 0;
+
+        true
+    }
+}
+
+/// Serialized as `Gene::code`
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    VariantCount,
+    Variantly,
+    Serialize,
+    Deserialize,
+)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum Gene {
+    MovePosition(Direction),
+    MoveEnergy(Direction),
+    Reproduction(Direction),
+    Synthesis(TypeSynthesis),
+    Attack(Direction),
+    Stop,
+    #[default]
+    None,
+}
+
+impl Gene {
+    pub const KINDS: [&'static str; Self::VARIANT_COUNT] = [
+        "move_position",
+        "move_energy",
+        "reproduction",
+        "synthesis",
+        "attack",
+        "stop",
+        "none",
+    ];
+
+    /// Index of the variant, same order as `KINDS`
+    #[inline]
+    pub fn kind(&self) -> usize {
+        match self {
+            Self::MovePosition(_) => 0,
+            Self::MoveEnergy(_) => 1,
+            Self::Reproduction(_) => 2,
+            Self::Synthesis(_) => 3,
+            Self::Attack(_) => 4,
+            Self::Stop => 5,
+            Self::None => 6,
+        }
+    }
+
+    /// Unique code of the gene: `kind * 8 + parameter`
+    #[inline]
+    pub fn code(&self) -> u8 {
+        let parameter = match self {
+            Self::MovePosition(direction)
+            | Self::MoveEnergy(direction)
+            | Self::Reproduction(direction)
+            | Self::Attack(direction) => *direction as u8,
+            Self::Synthesis(type_synthesis) => *type_synthesis as u8,
+            Self::Stop | Self::None => 0,
+        };
+        self.kind() as u8 * 8 + parameter
+    }
+
+    /// Inverse of `code`
+    pub fn from_code(code: u8) -> Option<Self> {
+        let parameter = (code % 8) as usize;
+        let direction = Direction::ALL.get(parameter).copied();
+        Some(match code / 8 {
+            0 => Self::MovePosition(direction?),
+            1 => Self::MoveEnergy(direction?),
+            2 => Self::Reproduction(direction?),
+            3 => Self::Synthesis(*TypeSynthesis::ALL.get(parameter)?),
+            4 => Self::Attack(direction?),
+            5 if parameter == 0 => Self::Stop,
+            6 if parameter == 0 => Self::None,
+            _ => return None,
+        })
+    }
+
+    /// Mutates the parameter with the rate of its type, `MutationProfile::direction`
+    /// or `MutationProfile::type_synthesis`. `Stop` and `None` have no parameter
+    /// and are replaced
+    pub fn mutate_parameter(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool {
+        match self {
+            Self::MovePosition(direction)
+            | Self::MoveEnergy(direction)
+            | Self::Reproduction(direction)
+            | Self::Attack(direction) => direction.mutate(profile, rng),
+            Self::Synthesis(type_synthesis) => type_synthesis.mutate(profile, rng),
+            Self::Stop | Self::None => {
+                *self = self.get_rand_variant(rng);
+                true
+            }
+        }
+    }
+}
+
+impl From<Gene> for u8 {
+    fn from(gene: Gene) -> Self {
+        gene.code()
+    }
+}
+
+impl TryFrom<u8> for Gene {
+    type Error = &'static str;
+
+    fn try_from(code: u8) -> Result<Self, Self::Error> {
+        Self::from_code(code).ok_or("unknown gene code")
+    }
+}
+
+impl Mutable for Gene {
+    fn mutate(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool {
+        if is_mutated(rng, profile.genes.of(self)) {
+            return match profile.operators.choose(rng) {
+                Operator::Substitute => {
+                    *self = self.get_rand_variant(rng);
+                    true
+                }
+                Operator::Parameter => self.mutate_parameter(profile, rng),
+            };
+        }
+
+        false
+    }
+}
+
+impl fmt::Display for Gene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MovePosition(direction) => write!(f, "move {}", direction),
+            Self::MoveEnergy(direction) => write!(f, "give {}", direction),
+            Self::Reproduction(direction) => write!(f, "divide {}", direction),
+            Self::Synthesis(type_synthesis) => write!(f, "synth {}", type_synthesis),
+            Self::Attack(direction) => write!(f, "attack {}", direction),
+            Self::Stop => write!(f, "stop"),
+            Self::None => write!(f, "nop"),
+        }
+    }
+}
+
+impl GetRandomVariant for Gene {
+    const VARIANT_COUNT: usize = Self::VARIANT_COUNT;
+
+    fn get_rand_variant(self, rng: &mut SimRng) -> Self {
+        match Self::gen_idx_variant(rng) {
+            0 => Self::MovePosition(Direction::Down.get_rand_variant(rng)),
+            1 => Self::MoveEnergy(Direction::Down.get_rand_variant(rng)),
+            2 => Self::Reproduction(Direction::Down.get_rand_variant(rng)),
+            3 => Self::Synthesis(TypeSynthesis::Energy.get_rand_variant(rng)),
+            4 => Self::Attack(Direction::Down.get_rand_variant(rng)),
+            5 => Self::Stop,
+            6 => Self::None,
+            idx => panic!("Unknown variant index: {};", idx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, VariantCount, Variantly)]
+pub enum TypeSynthesis {
+    Energy,
+    Toxin,
+    Health,
+}
+
+impl TypeSynthesis {
+    /// In the order of declaration
+    pub const ALL: [Self; Self::VARIANT_COUNT] = [Self::Energy, Self::Toxin, Self::Health];
+}
+
+impl Mutable for TypeSynthesis {
+    fn mutate(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool {
+        if is_mutated(rng, profile.type_synthesis) {
+            *self = self.get_rand_variant(rng);
+            return true;
+        }
+
+        false
+    }
+}
+
+impl fmt::Display for TypeSynthesis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Energy => "energy",
+            Self::Toxin => "toxin",
+            Self::Health => "health",
+        })
+    }
+}
+
+impl GetRandomVariant for TypeSynthesis {
+    const VARIANT_COUNT: usize = Self::VARIANT_COUNT;
+
+    fn get_rand_variant(self, rng: &mut SimRng) -> Self {
+        match Self::gen_idx_variant(rng) {
+            0 => Self::Energy,
+            1 => Self::Toxin,
+            2 => Self::Health,
+            idx => panic!("Unknown variant index: {};", idx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+    use crate::{
+        etc::{decode, encode},
+        mutation::OperatorWeights,
+    };
+
+    fn all_genes() -> Vec<Gene> {
+        (0..=u8::MAX).filter_map(Gene::from_code).collect()
+    }
+
+    #[test]
+    fn code_round_trip() {
+        let genes = all_genes();
+        assert_eq!(
+            genes.len(),
+            4 * Direction::ALL.len() + TypeSynthesis::ALL.len() + 2
+        );
+        for gene in genes {
+            assert_eq!(Gene::from_code(gene.code()), Some(gene));
+            assert_eq!(gene.kind() as u8, gene.code() / 8);
+        }
+    }
+
+    #[test]
+    fn genome_round_trip() {
+        let mut genome = Genome::new(10);
+        genome.set(3, Gene::Attack(Direction::Top));
+        genome.step = 4;
+
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &genome).unwrap();
+        let decoded: Genome = decode(bytes.as_slice()).unwrap();
+        assert_eq!(decoded, genome);
+        assert_eq!(decoded.len(), 10);
+        assert_eq!(decoded.step, 4);
+    }
+
+    #[test]
+    fn oversized_genome_is_rejected() {
+        let repr = GenomeRepr {
+            step: 0,
+            genes: vec![Gene::None; MAX_COUNT_GENES + 1],
+        };
+        let mut bytes = Vec::new();
+        encode(&mut bytes, &repr).unwrap();
+        let e = decode::<Genome, _>(bytes.as_slice()).unwrap_err();
+        assert!(
+            e.to_string().contains(&MAX_COUNT_GENES.to_string()),
+            "{}",
+            e
+        );
+    }
+
+    #[test]
+    fn parameter_operator_uses_the_rates_of_the_types() {
+        let mut profile = MutationProfile::default();
+        profile.genes.iter_mut().for_each(|rate| *rate = 1.0);
+        profile.operators = OperatorWeights {
+            substitute: 0.0,
+            parameter: 1.0,
+        };
+        profile.direction = 0.0;
+        profile.type_synthesis = 0.0;
+        let mut rng = SimRng::seed_from_u64(4);
+        for mut gene in all_genes() {
+            let before = gene;
+            let mutated = gene.mutate(&profile, &mut rng);
+            // only the genes without a parameter are replaced
+            assert_eq!(mutated, matches!(before, Gene::Stop | Gene::None));
+            if !mutated {
+                assert_eq!(gene, before);
+            }
+        }
+
+        profile.direction = 1.0;
+        profile.type_synthesis = 1.0;
+        let mut changed = 0;
+        for _ in 0..10 {
+            for mut gene in all_genes() {
+                let before = gene;
+                assert!(gene.mutate(&profile, &mut rng));
+                if !matches!(before, Gene::Stop | Gene::None) {
+                    assert_eq!(gene.kind(), before.kind());
+                }
+                changed += (gene != before) as usize;
+            }
+        }
+        assert!(changed > 0);
+    }
+
+    #[test]
+    fn genes_without_a_rate_are_kept() {
+        let mut profile = MutationProfile::default();
+        profile.genes.iter_mut().for_each(|rate| *rate = 0.0);
+        let mut rng = SimRng::seed_from_u64(1);
+        for mut gene in all_genes() {
+            let before = gene;
+            assert!(!gene.mutate(&profile, &mut rng));
+            assert_eq!(gene, before);
+        }
+
+        let mut genome = Genome::new(MAX_COUNT_GENES);
+        let before = genome;
+        genome.mutate(&profile, &mut rng);
+        assert_eq!(genome, before);
+    }
+}
@@ -1,9 +1,26 @@
 pub mod cell;
+pub mod census;
+pub mod checkpoint;
+pub mod colormap;
+pub mod config;
 pub mod consts;
+pub mod diversity;
 pub mod etc;
 pub mod genome;
+pub mod lineage;
 pub mod math;
+pub mod mutation;
+pub mod observer;
+pub mod phylogeny;
+pub mod pool;
+pub mod render;
+pub mod replay;
+pub mod stats;
+pub mod timelapse;
 pub mod traits;
 pub mod world;
 
+#[cfg(test)]
+mod testing;
+
 pub mod client;
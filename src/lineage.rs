@@ -0,0 +1,162 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::cell::{CellId, DeathCause};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum LineageEvent {
+    /// `parent` is `NO_PARENT` for the seeded cells
+    Birth {
+        tick: u64,
+        id: CellId,
+        parent: CellId,
+        clade: CellId,
+    },
+    /// A mutant founds a new clade, `clade` is the id of the mutant
+    Speciation {
+        tick: u64,
+        id: CellId,
+        parent_clade: CellId,
+        clade: CellId,
+    },
+    Death {
+        tick: u64,
+        id: CellId,
+        clade: CellId,
+        cause: DeathCause,
+    },
+}
+
+impl LineageEvent {
+    #[inline]
+    pub fn tick(&self) -> u64 {
+        match self {
+            LineageEvent::Birth { tick, .. }
+            | LineageEvent::Speciation { tick, .. }
+            | LineageEvent::Death { tick, .. } => *tick,
+        }
+    }
+}
+
+/// Writes the events as JSON lines
+pub struct LineageWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> LineageWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    pub fn write<'a, I>(&mut self, events: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = &'a LineageEvent>,
+    {
+        for event in events {
+            serde_json::to_writer(&mut self.inner, event)?;
+            self.inner.write_all(b"\n")?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{cell::NO_PARENT, testing, world::World};
+
+    #[test]
+    fn ids_and_clades_of_a_world() {
+        let mut config = testing::config(3);
+        config.mutation.cell = 0.3;
+        let mut world = World::new(config);
+        world.set_lineage_recording(true);
+        testing::populate(&mut world, 6);
+        let mut events: Vec<LineageEvent> = world.drain_lineage().collect();
+        for _ in 0..40 {
+            world.update();
+            events.extend(world.drain_lineage());
+        }
+
+        // the clade of each cell that is born
+        let mut clades: HashMap<CellId, CellId> = HashMap::new();
+        let mut living = 0;
+        let (mut births, mut speciations, mut deaths) = (0, 0, 0);
+        for event in &events {
+            match *event {
+                LineageEvent::Birth {
+                    id, parent, clade, ..
+                } => {
+                    births += 1;
+                    assert!(id != NO_PARENT && !clades.contains_key(&id), "reused id");
+                    if parent == NO_PARENT {
+                        assert_eq!(clade, id, "a seeded cell founds its own clade");
+                    } else {
+                        assert!(clades.contains_key(&parent), "unknown parent");
+                        assert!(clade == clades[&parent] || clade == id);
+                    }
+                    clades.insert(id, clade);
+                    living += 1;
+                }
+                LineageEvent::Speciation {
+                    id,
+                    parent_clade,
+                    clade,
+                    ..
+                } => {
+                    speciations += 1;
+                    assert_eq!(clade, id, "a mutant founds the clade of its id");
+                    assert_eq!(clades[&id], clade);
+                    assert_ne!(parent_clade, clade);
+                }
+                LineageEvent::Death { id, clade, .. } => {
+                    deaths += 1;
+                    assert_eq!(clades.get(&id), Some(&clade), "death of an unknown cell");
+                    living -= 1;
+                }
+            }
+        }
+        assert!(births > 0 && speciations > 0 && deaths > 0);
+        assert_eq!(living, world.count_cells());
+    }
+
+    #[test]
+    fn events_are_json_lines() {
+        let events = [
+            LineageEvent::Birth {
+                tick: 0,
+                id: 1,
+                parent: NO_PARENT,
+                clade: 1,
+            },
+            LineageEvent::Death {
+                tick: 7,
+                id: 1,
+                clade: 1,
+                cause: DeathCause::Starvation,
+            },
+        ];
+        let mut writer = LineageWriter::new(Vec::new());
+        writer.write(&events).unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        for (line, event) in lines.iter().zip(&events) {
+            assert_eq!(serde_json::from_str::<LineageEvent>(line).unwrap(), *event);
+        }
+        assert!(lines[1].contains("\"event\":\"death\""));
+    }
+}
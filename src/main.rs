@@ -1,12 +1,341 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
+
 #[cfg(feature = "sdl3")]
 use evocell::client::sdl::AppSdl;
+#[cfg(feature = "term")]
+use evocell::client::term::AppTerm;
+
+use evocell::{
+    checkpoint::{self, Checkpointer},
+    client::{clock::SimClock, headless::AppHeadless, traits::App},
+    config::SimConfig,
+    render::{Frame, FrameExporter, ImageFormat, ModRender},
+    stats::StatsWriter,
+    timelapse::Timelapse,
+    world::World,
+};
+
+const USAGE: &str = "usage: evocell [CONFIG.toml] [--headless | --term] [--ticks N] [--threads N] \
+[--tps N] [--max-catch-up MS] [--checkpoint-dir DIR] [--checkpoint-every TICKS] [--checkpoint-minutes M] [--checkpoint-keep K] \
+[--frames-dir DIR] [--frames-every TICKS] [--frames-scale S] [--frames-mode MODE] \
+[--frames-format png|ppm] [--timelapse OUT.gif|DIR] [--timelapse-every TICKS] [--timelapse-scale S] \
+[--timelapse-mode MODE] [--timelapse-fps FPS] [--stats OUT.csv] [--stats-every TICKS]
+
+MODE: default|energy|toxin|health|family|lifetime|genotype|gene|step
+--threads: workers of the striped update (stripe_height in the config), all the cores by default
+--tps: ticks per second at speed 1, --max-catch-up: milliseconds of late ticks run at once";
+
+/// Checkpoints are written every 10 minutes unless an interval is given
+const DEFAULT_CHECKPOINT_MINUTES: f64 = 10.0;
+const DEFAULT_FRAMES_EVERY: u64 = 1000;
+const DEFAULT_TIMELAPSE_EVERY: u64 = 100;
+const DEFAULT_STATS_EVERY: u64 = 100;
+
+#[derive(Debug, Default)]
+struct Args {
+    config: Option<String>,
+    headless: bool,
+    /// Draws in the terminal instead of a window
+    term: bool,
+    ticks: Option<u64>,
+    threads: Option<usize>,
+    tps: Option<f64>,
+    max_catch_up: Option<u64>,
+    checkpoint_dir: Option<String>,
+    checkpoint_every: Option<u64>,
+    checkpoint_minutes: Option<f64>,
+    checkpoint_keep: Option<usize>,
+    frames_dir: Option<String>,
+    frames_every: Option<u64>,
+    frames_scale: Option<u32>,
+    frames_mode: Option<ModRender>,
+    frames_format: Option<ImageFormat>,
+    timelapse: Option<String>,
+    timelapse_every: Option<u64>,
+    timelapse_scale: Option<u32>,
+    timelapse_mode: Option<ModRender>,
+    timelapse_fps: Option<u32>,
+    stats: Option<String>,
+    stats_every: Option<u64>,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Self::default();
+        let mut iter = std::env::args().skip(1);
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().ok_or(format!("{} requires a value", arg));
+            match arg.as_str() {
+                "--headless" => args.headless = true,
+                "--term" => args.term = true,
+                "--ticks" => args.ticks = Some(parse(&arg, value()?)?),
+                "--threads" => args.threads = Some(parse(&arg, value()?)?),
+                "--tps" => args.tps = Some(parse(&arg, value()?)?),
+                "--max-catch-up" => args.max_catch_up = Some(parse(&arg, value()?)?),
+                "--checkpoint-dir" => args.checkpoint_dir = Some(value()?),
+                "--checkpoint-every" => args.checkpoint_every = Some(parse(&arg, value()?)?),
+                "--checkpoint-minutes" => args.checkpoint_minutes = Some(parse(&arg, value()?)?),
+                "--checkpoint-keep" => args.checkpoint_keep = Some(parse(&arg, value()?)?),
+                "--frames-dir" => args.frames_dir = Some(value()?),
+                "--frames-every" => args.frames_every = Some(parse(&arg, value()?)?),
+                "--frames-scale" => args.frames_scale = Some(parse(&arg, value()?)?),
+                "--frames-mode" => args.frames_mode = Some(parse(&arg, value()?)?),
+                "--frames-format" => args.frames_format = Some(parse(&arg, value()?)?),
+                "--timelapse" => args.timelapse = Some(value()?),
+                "--timelapse-every" => args.timelapse_every = Some(parse(&arg, value()?)?),
+                "--timelapse-scale" => args.timelapse_scale = Some(parse(&arg, value()?)?),
+                "--timelapse-mode" => args.timelapse_mode = Some(parse(&arg, value()?)?),
+                "--timelapse-fps" => args.timelapse_fps = Some(parse(&arg, value()?)?),
+                "--stats" => args.stats = Some(value()?),
+                "--stats-every" => args.stats_every = Some(parse(&arg, value()?)?),
+                "-h" | "--help" => return Err(USAGE.to_string()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+                _ if args.config.is_none() => args.config = Some(arg),
+                _ => return Err(format!("unexpected argument {}", arg)),
+            }
+        }
+
+        if args.tps.is_some_and(|tps| !tps.is_finite() || tps <= 0.0) {
+            return Err("--tps must be a positive number".to_string());
+        }
+        // also rejects the intervals that do not fit a `Duration`
+        if let Some(minutes) = args.checkpoint_minutes
+            && Duration::try_from_secs_f64(minutes * 60.0).is_err()
+        {
+            return Err("--checkpoint-minutes must be a number of minutes, 0 or more".to_string());
+        }
+        Ok(args)
+    }
+}
+
+fn parse<T: std::str::FromStr>(arg: &str, value: String) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value of {}: {}", arg, value))
+}
 
-use evocell::client::traits::App;
+fn exit_with(msg: impl std::fmt::Display) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
 
 pub fn main() {
-    #[cfg(feature = "sdl3")]
+    let args = Args::parse().unwrap_or_else(|e| exit_with(e));
+
+    let config = match &args.config {
+        Some(path) => {
+            SimConfig::from_file(path).unwrap_or_else(|e| exit_with(format!("{}: {}", path, e)))
+        }
+        None => SimConfig::default(),
+    };
+
+    let mut world = None;
+    let mut checkpointer = None;
+    if let Some(dir) = &args.checkpoint_dir {
+        match checkpoint::load_latest(dir) {
+            Ok(Some((path, restored))) => {
+                eprintln!(
+                    "resumed from {} at tick {}",
+                    path.display(),
+                    restored.tick()
+                );
+                if let Some(config) = &args.config {
+                    eprintln!("{}: ignored, the checkpoint has its own config", config);
+                }
+                world = Some(restored);
+            }
+            Ok(None) => {}
+            Err(e) => exit_with(format!("{}: {}", dir, e)),
+        }
+
+        let mut c = match Checkpointer::new(dir) {
+            Ok(c) => c,
+            Err(e) => exit_with(format!("{}: {}", dir, e)),
+        };
+        if let Some(ticks) = args.checkpoint_every {
+            c = c.with_interval_ticks(ticks);
+        }
+        if args.checkpoint_minutes.is_some() || args.checkpoint_every.is_none() {
+            let minutes = args
+                .checkpoint_minutes
+                .unwrap_or(DEFAULT_CHECKPOINT_MINUTES);
+            c = c.with_interval(Duration::from_secs_f64(minutes * 60.0));
+        }
+        if let Some(keep) = args.checkpoint_keep {
+            c = c.with_keep(keep);
+        }
+        checkpointer = Some(Arc::new(Mutex::new(c)));
+    }
+
+    let mut world = world.unwrap_or_else(|| {
+        let mut world = World::new(config);
+        world.spawn_founder();
+        world
+    });
+    if args.threads.is_some() && world.config().stripe_height == 0 {
+        eprintln!("--threads has no effect without stripe_height in the config");
+    }
+    let threads = args
+        .threads
+        .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |threads| threads.get()));
+    if let Err(e) = world.set_threads(threads) {
+        exit_with(format!("failed to start the workers: {}", e));
+    }
+    if let Some(checkpointer) = &checkpointer {
+        world.add_observer(Box::new(checkpointer.clone()));
+    }
+    for (option, scale) in [
+        ("--frames-scale", args.frames_scale),
+        ("--timelapse-scale", args.timelapse_scale),
+    ] {
+        if let Some(scale) = scale
+            && Frame::render_size(&world, scale).is_none()
+        {
+            exit_with(format!(
+                "{} {}: the frames would be too large",
+                option, scale
+            ));
+        }
+    }
+    if let Some(dir) = &args.frames_dir {
+        let exporter =
+            match FrameExporter::new(dir, args.frames_every.unwrap_or(DEFAULT_FRAMES_EVERY)) {
+                Ok(exporter) => exporter,
+                Err(e) => exit_with(format!("{}: {}", dir, e)),
+            };
+        let mut exporter = exporter
+            .with_mode(args.frames_mode.unwrap_or_default())
+            .with_format(args.frames_format.unwrap_or_default());
+        if let Some(scale) = args.frames_scale {
+            exporter = exporter.with_scale(scale);
+        }
+        world.add_observer(Box::new(exporter));
+    }
+
+    let mut timelapse = None;
+    if let Some(path) = &args.timelapse {
+        let every = args.timelapse_every.unwrap_or(DEFAULT_TIMELAPSE_EVERY);
+        let gif = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("gif"));
+        let created = if gif {
+            Timelapse::gif(path, every)
+        } else {
+            Timelapse::frames(path, every, ImageFormat::Png)
+        };
+        let mut t = match created {
+            Ok(t) => t.with_mode(args.timelapse_mode.unwrap_or_default()),
+            Err(e) => exit_with(format!("{}: {}", path, e)),
+        };
+        if let Some(scale) = args.timelapse_scale {
+            t = t.with_scale(scale);
+        }
+        if let Some(fps) = args.timelapse_fps {
+            t = t.with_fps(fps);
+        }
+        let t = Arc::new(Mutex::new(t));
+        world.add_observer(Box::new(t.clone()));
+        timelapse = Some(t);
+    }
+
+    let mut stats = None;
+    if let Some(path) = &args.stats {
+        let every = args.stats_every.unwrap_or(DEFAULT_STATS_EVERY);
+        let created = File::create(path)
+            .and_then(|file| StatsWriter::new(BufWriter::new(file), &world, every));
+        let s = match created {
+            Ok(s) => Arc::new(Mutex::new(s)),
+            Err(e) => exit_with(format!("{}: {}", path, e)),
+        };
+        world.add_observer(Box::new(s.clone()));
+        stats = Some(s);
+    }
+
+    let mut sim_clock = None;
+    if args.tps.is_some() || args.max_catch_up.is_some() {
+        let mut clock = SimClock::new();
+        if let Some(tps) = args.tps {
+            clock = clock.with_tps(tps);
+        }
+        if let Some(ms) = args.max_catch_up {
+            clock = clock.with_max_catch_up(Duration::from_millis(ms));
+        }
+        sim_clock = Some(clock);
+    }
+
+    let outputs = Outputs {
+        checkpointer,
+        timelapse,
+        stats,
+    };
+    if args.headless || (!args.term && cfg!(not(feature = "sdl3"))) {
+        run(
+            AppHeadless::with_world(world).with_ticks(args.ticks),
+            sim_clock,
+            outputs,
+        );
+    } else if args.term {
+        #[cfg(feature = "term")]
+        run(AppTerm::with_world(world), sim_clock, outputs);
+        #[cfg(not(feature = "term"))]
+        exit_with("--term requires the term feature");
+    } else {
+        #[cfg(feature = "sdl3")]
+        run(AppSdl::with_world(world), sim_clock, outputs);
+    }
+}
+
+/// Outputs completed once the main loop stops
+struct Outputs {
+    checkpointer: Option<Arc<Mutex<Checkpointer>>>,
+    timelapse: Option<Arc<Mutex<Timelapse>>>,
+    stats: Option<Arc<Mutex<StatsWriter<BufWriter<File>>>>>,
+}
+
+/// `sim_clock` is the scheduler given by `--tps` or `--max-catch-up`
+fn run<A: App>(mut app: A, sim_clock: Option<SimClock>, outputs: Outputs) {
+    if let Some(clock) = sim_clock {
+        app = app.with_sim_clock(clock);
+    }
+    let mut app = app.init().unwrap_or_else(|e| exit_with(e)).run();
+    let panic = app.take_sim_panic();
+
+    // a closed window, the end of the run or a panic of the simulation must
+    // not lose the last ticks, an observer may have panicked with its lock
+    let mut failed = false;
+    if let Some(checkpointer) = outputs.checkpointer
+        && let Err(e) = lock(&checkpointer).save(app.world())
+    {
+        eprintln!("failed to write checkpoint: {}", e);
+        failed = true;
+    }
+    if let Some(timelapse) = outputs.timelapse
+        && let Err(e) = lock(&timelapse).finish()
+    {
+        eprintln!("failed to write timelapse: {}", e);
+        failed = true;
+    }
+    if let Some(stats) = outputs.stats
+        && let Err(e) = lock(&stats).finish()
     {
-        let app = AppSdl::new();
-        app.init().run();
+        eprintln!("failed to write stats: {}", e);
+        failed = true;
     }
+
+    if let Some(panic) = panic {
+        std::panic::resume_unwind(panic);
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
 }
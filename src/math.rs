@@ -1,26 +1,15 @@
 use crate::pos;
+use serde::{Deserialize, Serialize};
 use variant_count::VariantCount;
 use variantly::Variantly;
 
 use crate::{
-    consts::RADIUS_PETRI_DISH,
-    etc::is_mutated,
+    etc::{SimRng, is_mutated},
+    mutation::MutationProfile,
     traits::{GetRandomVariant, Mutable},
 };
 
-const WIDTH_WORLD: i32 = RADIUS_PETRI_DISH * 2;
-
-#[inline(always)]
-pub const fn get_index(pos: Position) -> i32 {
-    pos.y * WIDTH_WORLD + pos.x
-}
-
-// #[inline(always)]
-// pub const fn get_position(index: usize) -> Position {
-//     Position::new(index % WIDTH_WORLD, index / WIDTH_WORLD)
-// }
-
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 pub struct Position {
     x: i32,
     y: i32,
@@ -31,9 +20,10 @@ impl Position {
         Self { x, y }
     }
 
+    /// Index in a row-major grid `width` wide, the position must be inside
     #[inline(always)]
-    pub const fn to_index(self) -> usize {
-        get_index(self) as usize
+    pub const fn to_index(self, width: i32) -> usize {
+        self.y as usize * width as usize + self.x as usize
     }
 
     #[inline(always)]
@@ -89,7 +79,7 @@ impl std::ops::Add<(i32, i32)> for Position {
     }
 }
 
-#[derive(Debug, Clone, Copy, VariantCount, Variantly)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, VariantCount, Variantly)]
 pub enum Direction {
     LeftDown,
     Left,
@@ -101,9 +91,23 @@ pub enum Direction {
     Down,
 }
 
-impl Into<(i32, i32)> for Direction {
-    fn into(self) -> (i32, i32) {
-        match self {
+impl Direction {
+    /// In the order of declaration
+    pub const ALL: [Self; Self::VARIANT_COUNT] = [
+        Self::LeftDown,
+        Self::Left,
+        Self::LeftTop,
+        Self::Top,
+        Self::RightTop,
+        Self::Right,
+        Self::RightDown,
+        Self::Down,
+    ];
+}
+
+impl From<Direction> for (i32, i32) {
+    fn from(value: Direction) -> Self {
+        match value {
             Direction::LeftDown => (-1, 1),
             Direction::Left => (-1, 0),
             Direction::LeftTop => (-1, -1),
@@ -117,9 +121,9 @@ impl Into<(i32, i32)> for Direction {
 }
 
 impl Mutable for Direction {
-    fn mutate(&mut self) -> bool {
-        if is_mutated(4.0) {
-            *self = self.get_rand_variant();
+    fn mutate(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool {
+        if is_mutated(rng, profile.direction) {
+            *self = self.get_rand_variant(rng);
             return true;
         }
 
@@ -127,11 +131,26 @@ impl Mutable for Direction {
     }
 }
 
+impl std::fmt::Display for Direction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Direction::LeftDown => "left_down",
+            Direction::Left => "left",
+            Direction::LeftTop => "left_top",
+            Direction::Top => "top",
+            Direction::RightTop => "right_top",
+            Direction::Right => "right",
+            Direction::RightDown => "right_down",
+            Direction::Down => "down",
+        })
+    }
+}
+
 impl GetRandomVariant for Direction {
     const VARIANT_COUNT: usize = Self::VARIANT_COUNT;
 
-    fn get_rand_variant(self) -> Self {
-        match Self::gen_idx_variant() {
+    fn get_rand_variant(self, rng: &mut SimRng) -> Self {
+        match Self::gen_idx_variant(rng) {
             0 => Self::LeftDown,
             1 => Self::Left,
             2 => Self::LeftTop,
@@ -0,0 +1,321 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::{ConfigError, check},
+    consts::{DEFAULT_RATE_DRIFT, MAX_DRIFT, MAX_OPERATOR_WEIGHT, PROBABILITY_OF_MUTATION},
+    etc::SimRng,
+    genome::Gene,
+};
+
+/// Mutation rates of a cell.
+///
+/// `cell` is the probability that a newborn is a mutant, every other rate is
+/// applied only to mutants. The profile is inherited by the offspring, a
+/// mutant perturbs `cell` by `rate_drift` and the other rates by `drift`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MutationProfile {
+    /// Probability that an offspring is a mutant
+    pub cell: f64,
+    /// Perturbation of `cell` of a mutant, see `perturb_rate`,
+    /// `0` keeps the rate of the seeded cells
+    pub rate_drift: f64,
+    /// Per locus probability to hit a gene, by the kind of the gene
+    pub genes: GeneRates,
+    /// Probabilities that `Operator::Parameter` changes a direction or a type
+    /// of synthesis, see `Gene::mutate_parameter`
+    pub direction: f64,
+    pub type_synthesis: f64,
+    pub color: f64,
+    pub family: f64,
+    pub max_lifetime: f64,
+    /// Probability that a mutant is `Cell::fixed`
+    pub fixed: f64,
+    /// Upper bound (exclusive) for `max_lifetime` of a mutant
+    pub max_lifetime_limit: u32,
+    pub operators: OperatorWeights,
+    /// Perturbation of the rates of a mutant, see `perturb_rate`
+    pub drift: f64,
+}
+
+impl MutationProfile {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let rates = [
+            self.cell,
+            self.direction,
+            self.type_synthesis,
+            self.color,
+            self.family,
+            self.max_lifetime,
+            self.fixed,
+        ];
+        check(
+            rates.iter().chain(self.genes.iter()).all(is_probability),
+            "mutation rates must be in 0..=1",
+        )?;
+        check(
+            self.max_lifetime_limit > 0,
+            "mutation.max_lifetime_limit must be positive",
+        )?;
+        check(
+            (0.0..=MAX_DRIFT).contains(&self.drift),
+            format!("mutation.drift must be in 0..={}", MAX_DRIFT),
+        )?;
+        check(
+            (0.0..=MAX_DRIFT).contains(&self.rate_drift),
+            format!("mutation.rate_drift must be in 0..={}", MAX_DRIFT),
+        )?;
+        self.operators.validate()
+    }
+
+    /// Perturbs every rate by a random factor
+    pub fn drift(&mut self, rng: &mut SimRng) {
+        if self.drift <= 0.0 {
+            return;
+        }
+
+        let drift = self.drift;
+        let mut perturb = |rate: &mut f64| *rate = perturb_rate(rng, *rate, drift);
+
+        self.genes.iter_mut().for_each(&mut perturb);
+        perturb(&mut self.direction);
+        perturb(&mut self.type_synthesis);
+        perturb(&mut self.color);
+        perturb(&mut self.family);
+        perturb(&mut self.max_lifetime);
+        perturb(&mut self.fixed);
+    }
+}
+
+impl Default for MutationProfile {
+    fn default() -> Self {
+        Self {
+            cell: PROBABILITY_OF_MUTATION,
+            rate_drift: DEFAULT_RATE_DRIFT,
+            genes: GeneRates::default(),
+            direction: PROBABILITY_OF_MUTATION * 4.0,
+            type_synthesis: PROBABILITY_OF_MUTATION * 10.0,
+            color: 1.0,
+            family: 1.0,
+            max_lifetime: 1.0,
+            fixed: PROBABILITY_OF_MUTATION,
+            max_lifetime_limit: 1000,
+            operators: OperatorWeights::default(),
+            drift: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneRates {
+    pub move_position: f64,
+    pub move_energy: f64,
+    pub reproduction: f64,
+    pub synthesis: f64,
+    pub attack: f64,
+    pub stop: f64,
+    pub none: f64,
+}
+
+impl GeneRates {
+    #[inline]
+    pub fn of(&self, gene: &Gene) -> f64 {
+        match gene {
+            Gene::MovePosition(_) => self.move_position,
+            Gene::MoveEnergy(_) => self.move_energy,
+            Gene::Reproduction(_) => self.reproduction,
+            Gene::Synthesis(_) => self.synthesis,
+            Gene::Attack(_) => self.attack,
+            Gene::Stop => self.stop,
+            Gene::None => self.none,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &f64> {
+        [
+            &self.move_position,
+            &self.move_energy,
+            &self.reproduction,
+            &self.synthesis,
+            &self.attack,
+            &self.stop,
+            &self.none,
+        ]
+        .into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut f64> {
+        [
+            &mut self.move_position,
+            &mut self.move_energy,
+            &mut self.reproduction,
+            &mut self.synthesis,
+            &mut self.attack,
+            &mut self.stop,
+            &mut self.none,
+        ]
+        .into_iter()
+    }
+}
+
+impl Default for GeneRates {
+    fn default() -> Self {
+        let rate = PROBABILITY_OF_MUTATION * 10.0;
+        Self {
+            move_position: rate,
+            move_energy: rate,
+            reproduction: rate,
+            synthesis: rate,
+            attack: rate,
+            stop: rate,
+            none: rate,
+        }
+    }
+}
+
+/// Relative weights of the operators applied to a hit gene
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OperatorWeights {
+    /// Replace the gene with a random one
+    pub substitute: f64,
+    /// Keep the kind of the gene and mutate its parameter
+    pub parameter: f64,
+}
+
+impl OperatorWeights {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let weights = 0.0..=MAX_OPERATOR_WEIGHT;
+        check(
+            weights.contains(&self.substitute) && weights.contains(&self.parameter),
+            format!(
+                "mutation.operators weights must be in 0..={}",
+                MAX_OPERATOR_WEIGHT
+            ),
+        )?;
+        check(
+            self.substitute + self.parameter > 0.0,
+            "mutation.operators weights must not all be zero",
+        )
+    }
+
+    pub fn choose(&self, rng: &mut SimRng) -> Operator {
+        let total = self.substitute + self.parameter;
+        if rng.gen_range(0.0..total) < self.substitute {
+            Operator::Substitute
+        } else {
+            Operator::Parameter
+        }
+    }
+}
+
+impl Default for OperatorWeights {
+    fn default() -> Self {
+        Self {
+            substitute: 1.0,
+            parameter: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Substitute,
+    Parameter,
+}
+
+/// Multiplies `rate` by `exp(x)`, `x` in `-drift..=drift`
+pub fn perturb_rate(rng: &mut SimRng, rate: f64, drift: f64) -> f64 {
+    if drift <= 0.0 {
+        return rate;
+    }
+
+    let k = rng.gen_range(-drift..=drift).exp();
+    (rate * k).clamp(0.0, 1.0)
+}
+
+#[inline(always)]
+fn is_probability(rate: &f64) -> bool {
+    (0.0..=1.0).contains(rate)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use super::*;
+
+    fn invalid(profile: MutationProfile) {
+        assert!(profile.validate().is_err(), "{:?}", profile);
+    }
+
+    #[test]
+    fn default_is_valid() {
+        MutationProfile::default().validate().unwrap();
+    }
+
+    #[test]
+    fn rates_must_be_probabilities() {
+        for rate in [-0.1, 1.1, f64::NAN] {
+            invalid(MutationProfile {
+                color: rate,
+                ..MutationProfile::default()
+            });
+            let mut profile = MutationProfile::default();
+            profile.genes.attack = rate;
+            invalid(profile);
+        }
+    }
+
+    #[test]
+    fn drift_and_weights_are_bounded() {
+        for value in [-1.0, f64::NAN, f64::INFINITY, MAX_OPERATOR_WEIGHT * 2.0] {
+            invalid(MutationProfile {
+                drift: value,
+                ..MutationProfile::default()
+            });
+            invalid(MutationProfile {
+                rate_drift: value,
+                ..MutationProfile::default()
+            });
+            invalid(MutationProfile {
+                operators: OperatorWeights {
+                    substitute: value,
+                    parameter: 1.0,
+                },
+                ..MutationProfile::default()
+            });
+        }
+        invalid(MutationProfile {
+            operators: OperatorWeights {
+                substitute: 0.0,
+                parameter: 0.0,
+            },
+            ..MutationProfile::default()
+        });
+    }
+
+    #[test]
+    fn largest_valid_values_are_usable() {
+        let mut profile = MutationProfile {
+            drift: MAX_DRIFT,
+            rate_drift: MAX_DRIFT,
+            operators: OperatorWeights {
+                substitute: MAX_OPERATOR_WEIGHT,
+                parameter: MAX_OPERATOR_WEIGHT,
+            },
+            ..MutationProfile::default()
+        };
+        profile.validate().unwrap();
+
+        let mut rng = SimRng::seed_from_u64(2);
+        for _ in 0..100 {
+            profile.operators.choose(&mut rng);
+            profile.drift(&mut rng);
+            assert!(profile.genes.iter().all(is_probability));
+            assert!(is_probability(&perturb_rate(&mut rng, 0.5, MAX_DRIFT)));
+        }
+    }
+}
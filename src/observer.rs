@@ -0,0 +1,384 @@
+use std::sync::{Arc, Mutex};
+
+use crate::{
+    cell::{Cell, DeathCause},
+    genome::Gene,
+    math::Position,
+    world::World,
+};
+
+pub type ObserverId = u64;
+
+/// Callbacks of the events of a `World`, registered with `World::add_observer`.
+///
+/// Every method does nothing by default. The cells are passed as they are
+/// at the moment of the event.
+pub trait Observer: Send {
+    /// `parent` is `None` for the cells added with `World::spawn`
+    fn on_birth(&mut self, _tick: u64, _parent: Option<&Cell>, _child: &Cell, _pos: Position) {}
+    fn on_death(&mut self, _tick: u64, _cell: &Cell, _pos: Position, _cause: DeathCause) {}
+    /// Called once per tick with the start and the end position of a cell
+    fn on_move(&mut self, _tick: u64, _cell: &Cell, _from: Position, _to: Position) {}
+    /// Called once per tick for every cell after it executed `gene`,
+    /// including the cells that died during the update
+    fn on_action(&mut self, _tick: u64, _cell: &Cell, _from: Position, _to: Position, _gene: Gene) {
+    }
+    fn on_attack(&mut self, _tick: u64, _attacker: &Cell, _victim: &Cell, _damage: f32) {}
+    fn on_energy_transfer(&mut self, _tick: u64, _from: &Cell, _to: &Cell, _amount: f32) {}
+    /// Called before `on_birth` of a mutant
+    fn on_mutation(&mut self, _tick: u64, _parent: &Cell, _child: &Cell) {}
+    /// Called at the end of `World::update`
+    fn on_tick(&mut self, _world: &World) {}
+    /// false - only `on_tick` is called, the events of the cells are not
+    /// collected for the observer. Asked once by `World::add_observer`
+    fn wants_events(&self) -> bool {
+        true
+    }
+}
+
+/// Lets the owner keep a handle to the observer
+impl<T: Observer> Observer for Arc<Mutex<T>> {
+    fn on_birth(&mut self, tick: u64, parent: Option<&Cell>, child: &Cell, pos: Position) {
+        self.lock().unwrap().on_birth(tick, parent, child, pos)
+    }
+
+    fn on_death(&mut self, tick: u64, cell: &Cell, pos: Position, cause: DeathCause) {
+        self.lock().unwrap().on_death(tick, cell, pos, cause)
+    }
+
+    fn on_move(&mut self, tick: u64, cell: &Cell, from: Position, to: Position) {
+        self.lock().unwrap().on_move(tick, cell, from, to)
+    }
+
+    fn on_action(&mut self, tick: u64, cell: &Cell, from: Position, to: Position, gene: Gene) {
+        self.lock().unwrap().on_action(tick, cell, from, to, gene)
+    }
+
+    fn on_attack(&mut self, tick: u64, attacker: &Cell, victim: &Cell, damage: f32) {
+        self.lock()
+            .unwrap()
+            .on_attack(tick, attacker, victim, damage)
+    }
+
+    fn on_energy_transfer(&mut self, tick: u64, from: &Cell, to: &Cell, amount: f32) {
+        self.lock()
+            .unwrap()
+            .on_energy_transfer(tick, from, to, amount)
+    }
+
+    fn on_mutation(&mut self, tick: u64, parent: &Cell, child: &Cell) {
+        self.lock().unwrap().on_mutation(tick, parent, child)
+    }
+
+    fn on_tick(&mut self, world: &World) {
+        self.lock().unwrap().on_tick(world)
+    }
+
+    fn wants_events(&self) -> bool {
+        self.lock().unwrap().wants_events()
+    }
+}
+
+/// Call of an `Observer` kept by `EventLog`
+#[derive(Debug, Clone)]
+pub enum Event {
+    Birth {
+        tick: u64,
+        parent: Option<Cell>,
+        child: Cell,
+        pos: Position,
+    },
+    Death {
+        tick: u64,
+        cell: Cell,
+        pos: Position,
+        cause: DeathCause,
+    },
+    Move {
+        tick: u64,
+        cell: Cell,
+        from: Position,
+        to: Position,
+    },
+    Action {
+        tick: u64,
+        cell: Cell,
+        from: Position,
+        to: Position,
+        gene: Gene,
+    },
+    Attack {
+        tick: u64,
+        attacker: Cell,
+        victim: Cell,
+        damage: f32,
+    },
+    EnergyTransfer {
+        tick: u64,
+        from: Cell,
+        to: Cell,
+        amount: f32,
+    },
+    Mutation {
+        tick: u64,
+        parent: Cell,
+        child: Cell,
+    },
+}
+
+impl Event {
+    /// Makes the same call on `observer`
+    pub fn replay(&self, observer: &mut dyn Observer) {
+        match *self {
+            Event::Birth {
+                tick,
+                ref parent,
+                ref child,
+                pos,
+            } => observer.on_birth(tick, parent.as_ref(), child, pos),
+            Event::Death {
+                tick,
+                ref cell,
+                pos,
+                cause,
+            } => observer.on_death(tick, cell, pos, cause),
+            Event::Move {
+                tick,
+                ref cell,
+                from,
+                to,
+            } => observer.on_move(tick, cell, from, to),
+            Event::Action {
+                tick,
+                ref cell,
+                from,
+                to,
+                gene,
+            } => observer.on_action(tick, cell, from, to, gene),
+            Event::Attack {
+                tick,
+                ref attacker,
+                ref victim,
+                damage,
+            } => observer.on_attack(tick, attacker, victim, damage),
+            Event::EnergyTransfer {
+                tick,
+                ref from,
+                ref to,
+                amount,
+            } => observer.on_energy_transfer(tick, from, to, amount),
+            Event::Mutation {
+                tick,
+                ref parent,
+                ref child,
+            } => observer.on_mutation(tick, parent, child),
+        }
+    }
+}
+
+/// Keeps the events in order to replay them later, e.g. on the thread of
+/// the observers. `on_tick` is not kept.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: Vec<Event>,
+}
+
+impl EventLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes the events kept since the last call
+    pub fn drain(&mut self) -> std::vec::Drain<'_, Event> {
+        self.events.drain(..)
+    }
+}
+
+impl Observer for EventLog {
+    fn on_birth(&mut self, tick: u64, parent: Option<&Cell>, child: &Cell, pos: Position) {
+        self.events.push(Event::Birth {
+            tick,
+            parent: parent.copied(),
+            child: *child,
+            pos,
+        });
+    }
+
+    fn on_death(&mut self, tick: u64, cell: &Cell, pos: Position, cause: DeathCause) {
+        self.events.push(Event::Death {
+            tick,
+            cell: *cell,
+            pos,
+            cause,
+        });
+    }
+
+    fn on_move(&mut self, tick: u64, cell: &Cell, from: Position, to: Position) {
+        self.events.push(Event::Move {
+            tick,
+            cell: *cell,
+            from,
+            to,
+        });
+    }
+
+    fn on_action(&mut self, tick: u64, cell: &Cell, from: Position, to: Position, gene: Gene) {
+        self.events.push(Event::Action {
+            tick,
+            cell: *cell,
+            from,
+            to,
+            gene,
+        });
+    }
+
+    fn on_attack(&mut self, tick: u64, attacker: &Cell, victim: &Cell, damage: f32) {
+        self.events.push(Event::Attack {
+            tick,
+            attacker: *attacker,
+            victim: *victim,
+            damage,
+        });
+    }
+
+    fn on_energy_transfer(&mut self, tick: u64, from: &Cell, to: &Cell, amount: f32) {
+        self.events.push(Event::EnergyTransfer {
+            tick,
+            from: *from,
+            to: *to,
+            amount,
+        });
+    }
+
+    fn on_mutation(&mut self, tick: u64, parent: &Cell, child: &Cell) {
+        self.events.push(Event::Mutation {
+            tick,
+            parent: *parent,
+            child: *child,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    /// Kind and tick of every call, `on_tick` with the tick of the world
+    #[derive(Default)]
+    struct Calls {
+        calls: Vec<(&'static str, u64)>,
+        log: EventLog,
+        events: bool,
+    }
+
+    impl Observer for Calls {
+        fn on_birth(&mut self, tick: u64, parent: Option<&Cell>, child: &Cell, pos: Position) {
+            self.calls.push(("birth", tick));
+            self.log.on_birth(tick, parent, child, pos);
+        }
+
+        fn on_death(&mut self, tick: u64, cell: &Cell, pos: Position, cause: DeathCause) {
+            self.calls.push(("death", tick));
+            self.log.on_death(tick, cell, pos, cause);
+        }
+
+        fn on_move(&mut self, tick: u64, cell: &Cell, from: Position, to: Position) {
+            self.calls.push(("move", tick));
+            self.log.on_move(tick, cell, from, to);
+        }
+
+        fn on_action(&mut self, tick: u64, cell: &Cell, from: Position, to: Position, gene: Gene) {
+            self.calls.push(("action", tick));
+            self.log.on_action(tick, cell, from, to, gene);
+        }
+
+        fn on_mutation(&mut self, tick: u64, parent: &Cell, child: &Cell) {
+            self.calls.push(("mutation", tick));
+            self.log.on_mutation(tick, parent, child);
+        }
+
+        fn on_tick(&mut self, world: &World) {
+            self.calls.push(("tick", world.tick()));
+        }
+
+        fn wants_events(&self) -> bool {
+            self.events
+        }
+    }
+
+    fn run(events: bool) -> Calls {
+        let mut config = testing::config(8);
+        config.mutation.cell = 0.5;
+        let mut world = World::new(config);
+        let calls = Arc::new(Mutex::new(Calls {
+            events,
+            ..Default::default()
+        }));
+        world.add_observer(Box::new(calls.clone()));
+        testing::populate(&mut world, 6);
+        for _ in 0..30 {
+            world.update();
+        }
+        drop(world);
+        Arc::into_inner(calls).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn events_of_a_tick_come_before_on_tick() {
+        let calls = run(true).calls;
+        let mut tick = 0;
+        for (kind, at) in calls {
+            if kind == "tick" {
+                tick += 1;
+                assert_eq!(at, tick, "on_tick once per update");
+            } else {
+                assert_eq!(at, tick, "{} of another tick", kind);
+            }
+        }
+        assert_eq!(tick, 30);
+    }
+
+    #[test]
+    fn mutation_precedes_the_birth_and_move_the_action() {
+        let mut log = run(true).log;
+        let events: Vec<Event> = log.drain().collect();
+        let (mut mutations, mut moves) = (0, 0);
+        for pair in events.windows(2) {
+            match (&pair[0], &pair[1]) {
+                (Event::Mutation { child, .. }, next) => {
+                    mutations += 1;
+                    assert!(
+                        matches!(next, Event::Birth { child: born, parent: Some(_), .. } if born.id == child.id)
+                    );
+                }
+                (Event::Move { cell, from, to, .. }, next) => {
+                    moves += 1;
+                    assert!(matches!(
+                        next,
+                        Event::Action { cell: acted, from: f, to: t, .. }
+                            if acted.id == cell.id && f == from && t == to
+                    ));
+                }
+                _ => {}
+            }
+        }
+        assert!(mutations > 0 && moves > 0);
+
+        // the seeded cells are born without a parent
+        assert!(matches!(events[0], Event::Birth { parent: None, .. }));
+
+        // a replay makes the same calls
+        let mut replayed = EventLog::new();
+        events.iter().for_each(|event| event.replay(&mut replayed));
+        let replayed: Vec<Event> = replayed.drain().collect();
+        assert_eq!(format!("{:?}", replayed), format!("{:?}", events));
+    }
+
+    #[test]
+    fn observer_without_events_only_ticks() {
+        let calls = run(false).calls;
+        assert_eq!(calls.len(), 30);
+        assert!(calls.iter().all(|(kind, _)| *kind == "tick"));
+    }
+}
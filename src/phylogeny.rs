@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell::{CellId, NO_PARENT},
+    lineage::LineageEvent,
+    world::World,
+};
+
+/// Tree of the clades, built from the lineage events of a `World`.
+///
+/// Call `record` after every `World::update`. Clades without living cells
+/// and living descendants are pruned as soon as they die out.
+#[derive(Debug, Default)]
+pub struct PhyloRecorder {
+    clades: HashMap<CellId, Clade>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Clade {
+    parent: CellId,
+    origin_tick: u64,
+    living: u64,
+    children: Vec<CellId>,
+}
+
+/// Flat form of the tree used by the JSON export
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhyloNode {
+    pub clade: CellId,
+    /// `NO_PARENT` for the roots
+    pub parent: CellId,
+    pub origin_tick: u64,
+    pub living: u64,
+}
+
+impl PhyloRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enables the lineage recording of `world`. The clades of the living
+    /// cells are the roots, their ancestry before `attach` is unknown.
+    pub fn attach(world: &mut World) -> Self {
+        world.set_lineage_recording(true);
+        // the living cells already include the events not drained yet
+        world.drain_lineage();
+
+        let mut recorder = Self::new();
+        for cell in world.living() {
+            let node = recorder.clades.entry(cell.clade).or_insert_with(|| Clade {
+                origin_tick: cell.birth_tick,
+                ..Default::default()
+            });
+            node.origin_tick = node.origin_tick.min(cell.birth_tick);
+            node.living += 1;
+        }
+        recorder
+    }
+
+    pub fn record(&mut self, world: &mut World) {
+        for event in world.drain_lineage() {
+            self.apply(&event);
+        }
+    }
+
+    pub fn apply(&mut self, event: &LineageEvent) {
+        match *event {
+            LineageEvent::Birth { tick, clade, .. } => {
+                let node = self.clades.entry(clade).or_insert_with(|| Clade {
+                    origin_tick: tick,
+                    ..Default::default()
+                });
+                node.living += 1;
+            }
+            LineageEvent::Speciation {
+                tick,
+                parent_clade,
+                clade,
+                ..
+            } => {
+                self.clades
+                    .entry(parent_clade)
+                    .or_insert_with(|| Clade {
+                        origin_tick: tick,
+                        ..Default::default()
+                    })
+                    .children
+                    .push(clade);
+
+                let node = self.clades.entry(clade).or_default();
+                node.parent = parent_clade;
+                node.origin_tick = tick;
+            }
+            LineageEvent::Death { clade, .. } => {
+                if let Some(node) = self.clades.get_mut(&clade) {
+                    node.living = node.living.saturating_sub(1);
+                    self.prune(clade);
+                }
+            }
+        }
+    }
+
+    /// Removes `clade` and its ancestors while they are extinct leaves
+    fn prune(&mut self, mut clade: CellId) {
+        loop {
+            let Some(node) = self.clades.get(&clade) else {
+                return;
+            };
+            if node.living > 0 || !node.children.is_empty() {
+                return;
+            }
+
+            let parent = node.parent;
+            self.clades.remove(&clade);
+            match self.clades.get_mut(&parent) {
+                Some(parent_node) => {
+                    parent_node.children.retain(|&child| child != clade);
+                    clade = parent;
+                }
+                None => return,
+            }
+        }
+    }
+
+    pub fn count_clades(&self) -> usize {
+        self.clades.len()
+    }
+
+    pub fn count_living_clades(&self) -> usize {
+        self.clades.values().filter(|node| node.living > 0).count()
+    }
+
+    fn roots(&self) -> Vec<CellId> {
+        let mut roots: Vec<CellId> = self
+            .clades
+            .iter()
+            .filter(|(_, node)| node.parent == NO_PARENT || !self.clades.contains_key(&node.parent))
+            .map(|(&clade, _)| clade)
+            .collect();
+        roots.sort_unstable();
+        roots
+    }
+
+    /// Clades are labeled `c<id>`, branch lengths are in ticks.
+    /// Several roots are joined under an unlabeled root.
+    pub fn to_newick(&self) -> String {
+        let roots = self.roots();
+        let mut out = String::new();
+
+        if roots.len() != 1 {
+            out.push('(');
+        }
+        for (i, &root) in roots.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            self.write_newick(root, &mut out);
+        }
+        if roots.len() != 1 {
+            out.push(')');
+        }
+        out.push(';');
+
+        out
+    }
+
+    fn write_newick(&self, root: CellId, out: &mut String) {
+        // (clade, index of the next child)
+        let mut stack = vec![(root, 0usize)];
+
+        while let Some((clade, next)) = stack.pop() {
+            let node = &self.clades[&clade];
+
+            if next < node.children.len() {
+                out.push(if next == 0 { '(' } else { ',' });
+                stack.push((clade, next + 1));
+                stack.push((node.children[next], 0));
+                continue;
+            }
+
+            if !node.children.is_empty() {
+                out.push(')');
+            }
+            let _ = write!(out, "c{}", clade);
+            if let Some(parent) = self.clades.get(&node.parent) {
+                let _ = write!(out, ":{}", node.origin_tick - parent.origin_tick);
+            }
+        }
+    }
+
+    /// Nodes sorted by clade id
+    pub fn nodes(&self) -> Vec<PhyloNode> {
+        let mut nodes: Vec<PhyloNode> = self
+            .clades
+            .iter()
+            .map(|(&clade, node)| PhyloNode {
+                clade,
+                parent: node.parent,
+                origin_tick: node.origin_tick,
+                living: node.living,
+            })
+            .collect();
+        nodes.sort_unstable_by_key(|node| node.clade);
+        nodes
+    }
+
+    pub fn write_newick_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", self.to_newick())
+    }
+
+    pub fn write_json_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(writer, &self.nodes()).map_err(io::Error::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cell::DeathCause, math::Position, testing};
+
+    fn seed(recorder: &mut PhyloRecorder, tick: u64, id: CellId) {
+        recorder.apply(&LineageEvent::Birth {
+            tick,
+            id,
+            parent: NO_PARENT,
+            clade: id,
+        });
+    }
+
+    /// Birth of the mutant `id` founding its own clade, as `World` records it
+    fn mutant(recorder: &mut PhyloRecorder, tick: u64, id: CellId, parent: CellId) {
+        recorder.apply(&LineageEvent::Birth {
+            tick,
+            id,
+            parent,
+            clade: id,
+        });
+        recorder.apply(&LineageEvent::Speciation {
+            tick,
+            id,
+            parent_clade: parent,
+            clade: id,
+        });
+    }
+
+    fn death(recorder: &mut PhyloRecorder, tick: u64, id: CellId) {
+        recorder.apply(&LineageEvent::Death {
+            tick,
+            id,
+            clade: id,
+            cause: DeathCause::Starvation,
+        });
+    }
+
+    #[test]
+    fn newick_of_one_tree() {
+        let mut recorder = PhyloRecorder::new();
+        seed(&mut recorder, 0, 1);
+        mutant(&mut recorder, 5, 2, 1);
+        mutant(&mut recorder, 8, 3, 1);
+        mutant(&mut recorder, 12, 4, 2);
+        assert_eq!(recorder.to_newick(), "((c4:7)c2:5,c3:8)c1;");
+        assert_eq!(recorder.count_clades(), 4);
+    }
+
+    #[test]
+    fn several_roots_are_joined() {
+        let mut recorder = PhyloRecorder::new();
+        seed(&mut recorder, 0, 1);
+        seed(&mut recorder, 0, 7);
+        mutant(&mut recorder, 3, 9, 7);
+        assert_eq!(recorder.to_newick(), "(c1,(c9:3)c7);");
+
+        let mut out = Vec::new();
+        recorder.write_newick_to(&mut out).unwrap();
+        assert_eq!(out, b"(c1,(c9:3)c7);\n");
+    }
+
+    #[test]
+    fn extinct_leaves_are_pruned() {
+        let mut recorder = PhyloRecorder::new();
+        seed(&mut recorder, 0, 1);
+        mutant(&mut recorder, 5, 2, 1);
+        mutant(&mut recorder, 8, 3, 1);
+
+        death(&mut recorder, 9, 3);
+        assert_eq!(recorder.to_newick(), "(c2:5)c1;");
+
+        // the extinct root is kept while a descendant lives
+        death(&mut recorder, 10, 1);
+        assert_eq!(recorder.count_clades(), 2);
+        assert_eq!(recorder.count_living_clades(), 1);
+
+        death(&mut recorder, 11, 2);
+        assert_eq!(recorder.count_clades(), 0);
+    }
+
+    #[test]
+    fn attach_mid_run_knows_the_living_clades() {
+        let mut config = testing::config(4);
+        config.mutation.cell = 0.3;
+        let mut world = World::new(config);
+        testing::populate(&mut world, 6);
+        for _ in 0..20 {
+            world.update();
+        }
+        let cell = world.new_cell();
+        world.spawn(Position::new(30, 20), cell);
+
+        let mut recorder = PhyloRecorder::attach(&mut world);
+        let mut clades: Vec<CellId> = world.living().map(|cell| cell.clade).collect();
+        clades.sort_unstable();
+        clades.dedup();
+        assert_eq!(recorder.count_living_clades(), clades.len());
+        assert_eq!(recorder.count_clades(), clades.len());
+
+        // every death is of a known clade, the living counts never run out
+        // while cells of the clade live
+        for _ in 0..60 {
+            world.update();
+            for event in world.drain_lineage().collect::<Vec<_>>() {
+                if let LineageEvent::Death { clade, .. } = event {
+                    assert!(
+                        recorder.clades[&clade].living > 0,
+                        "death in an empty clade"
+                    );
+                }
+                recorder.apply(&event);
+            }
+        }
+        let mut living: HashMap<CellId, u64> = HashMap::new();
+        for cell in world.living() {
+            *living.entry(cell.clade).or_default() += 1;
+        }
+        for (clade, node) in &recorder.clades {
+            assert_eq!(node.living, living.get(clade).copied().unwrap_or(0));
+        }
+    }
+
+    #[test]
+    fn nodes_are_sorted() {
+        let mut recorder = PhyloRecorder::new();
+        seed(&mut recorder, 0, 5);
+        mutant(&mut recorder, 2, 6, 5);
+        seed(&mut recorder, 0, 1);
+        let nodes = recorder.nodes();
+        assert_eq!(
+            nodes.iter().map(|node| node.clade).collect::<Vec<_>>(),
+            [1, 5, 6]
+        );
+        assert_eq!(nodes[2].parent, 5);
+        assert_eq!(nodes[0].parent, NO_PARENT);
+    }
+}
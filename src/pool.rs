@@ -0,0 +1,98 @@
+use std::{
+    io,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, Receiver, Sender},
+    },
+    thread::{self, JoinHandle},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Fixed set of threads running the jobs of `run`, kept for the whole
+/// simulation so an update does not pay for spawning them.
+pub struct WorkerPool {
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl WorkerPool {
+    pub fn new(threads: usize) -> io::Result<Self> {
+        let (jobs, jobs_rx) = mpsc::channel();
+        let jobs_rx = Arc::new(Mutex::new(jobs_rx));
+        let mut pool = Self {
+            jobs: Some(jobs),
+            workers: Vec::with_capacity(threads),
+        };
+        for index in 0..threads.max(1) {
+            let jobs_rx = Arc::clone(&jobs_rx);
+            // a failed spawn drops the pool, which stops the workers spawned so far
+            let worker = thread::Builder::new()
+                .name(format!("worker-{}", index))
+                .spawn(move || work(&jobs_rx))?;
+            pool.workers.push(worker);
+        }
+        Ok(pool)
+    }
+
+    #[inline(always)]
+    pub fn threads(&self) -> usize {
+        self.workers.len()
+    }
+
+    /// Runs the jobs on the workers and returns their results in the order
+    /// of `jobs`. A panic of a job is resumed on the calling thread.
+    pub fn run<R, F>(&self, jobs: Vec<F>) -> Vec<R>
+    where
+        R: Send + 'static,
+        F: FnOnce() -> R + Send + 'static,
+    {
+        let count = jobs.len();
+        let (results_tx, results) = mpsc::channel();
+        let sender = self.jobs.as_ref().expect("the pool is running");
+        for (index, job) in jobs.into_iter().enumerate() {
+            let results_tx = results_tx.clone();
+            sender
+                .send(Box::new(move || {
+                    let result = panic::catch_unwind(AssertUnwindSafe(job));
+                    let _ = results_tx.send((index, result));
+                }))
+                .expect("the workers are running");
+        }
+        drop(results_tx);
+
+        let mut slots: Vec<Option<R>> = (0..count).map(|_| None).collect();
+        for (index, result) in results {
+            match result {
+                Ok(result) => slots[index] = Some(result),
+                Err(panic) => panic::resume_unwind(panic),
+            }
+        }
+        slots
+            .into_iter()
+            .map(|slot| slot.expect("every job sends its result"))
+            .collect()
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // the workers leave once the channel is closed
+        self.jobs.take();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn work(jobs: &Mutex<Receiver<Job>>) {
+    loop {
+        // the lock is released before the job runs
+        let job = jobs.lock().unwrap().recv();
+        match job {
+            Ok(job) => job(),
+            Err(_) => return,
+        }
+    }
+}
@@ -0,0 +1,677 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    cell::Cell,
+    colormap::{Colormap, categorical, qualitative},
+    config::CellConfig,
+    genome::Gene,
+    observer::Observer,
+    world::World,
+};
+
+pub type Rgb = (u8, u8, u8);
+
+pub const BACKGROUND: Rgb = (25, 25, 30);
+pub const WALL: Rgb = (90, 90, 100);
+
+/// How the cells are coloured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModRender {
+    #[default]
+    Default,
+    Energy,
+    Toxin,
+    Health,
+    Family,
+    /// `lifetime` relative to `max_lifetime`
+    Lifetime,
+    Genotype,
+    /// Kind of the gene executed by the last update
+    Gene,
+    /// `genome.step` relative to the length of the genome
+    Step,
+}
+
+/// Number of the most common families or genotypes in the legend
+const LEGEND_TOP: usize = 6;
+/// Positions of the legend entries of a gradient
+const LEGEND_STOPS: [f32; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+const NO_GENE: Rgb = (70, 70, 70);
+
+impl ModRender {
+    pub const ALL: [Self; 9] = [
+        Self::Default,
+        Self::Energy,
+        Self::Toxin,
+        Self::Health,
+        Self::Family,
+        Self::Lifetime,
+        Self::Genotype,
+        Self::Gene,
+        Self::Step,
+    ];
+
+    /// The following mode of `ALL`, wraps around
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn color(&self, cell: &Cell, config: &CellConfig) -> Rgb {
+        match self {
+            ModRender::Default => cell.color,
+            ModRender::Energy | ModRender::Toxin | ModRender::Health => {
+                let scale = self.scale(config).unwrap();
+                Colormap::Viridis.sample(scale.normalize(self.value(cell)))
+            }
+            ModRender::Lifetime | ModRender::Step => inferno(self.value(cell)),
+            ModRender::Family => categorical(cell.family as u64),
+            ModRender::Genotype => categorical(cell.genome.genotype_hash()),
+            ModRender::Gene => match cell.last_gene {
+                Some(gene) => qualitative(gene.kind(), Gene::VARIANT_COUNT),
+                None => NO_GENE,
+            },
+        }
+    }
+
+    /// Quantity shown by the gradient modes, `Lifetime` and `Step` are
+    /// already in `0..=1`
+    fn value(&self, cell: &Cell) -> f32 {
+        match self {
+            ModRender::Energy => cell.energy,
+            ModRender::Toxin => cell.toxin,
+            ModRender::Health => cell.health,
+            ModRender::Lifetime => cell.lifetime as f32 / cell.max_lifetime.max(1) as f32,
+            ModRender::Step => cell.genome.step as f32 / (cell.genome.len() - 1).max(1) as f32,
+            _ => 0.0,
+        }
+    }
+
+    fn scale(&self, config: &CellConfig) -> Option<Scale> {
+        match self {
+            // a cell dies outside of the range
+            ModRender::Energy => Some(Scale::Log {
+                min: config.min_energy.max(f32::EPSILON),
+                max: config.max_energy,
+            }),
+            // a synthesis adds one unit
+            ModRender::Toxin => Some(Scale::Saturating { half: 1.0 }),
+            ModRender::Health => Some(Scale::Saturating {
+                half: config.initial_health,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Explains the colours of the mode, the categories are the most common
+    /// ones in `world`
+    pub fn legend(&self, world: &World) -> Legend {
+        let config = &world.config().cell;
+        let mut entries = Vec::new();
+        match self {
+            ModRender::Default => {}
+            ModRender::Energy | ModRender::Toxin | ModRender::Health => {
+                let scale = self.scale(config).unwrap();
+                for t in LEGEND_STOPS {
+                    let value = scale.value(t);
+                    let label = if value.is_finite() {
+                        format!("{:.2}", value)
+                    } else {
+                        "inf".to_string()
+                    };
+                    entries.push((label, Colormap::Viridis.sample(t)));
+                }
+            }
+            ModRender::Lifetime | ModRender::Step => {
+                for t in LEGEND_STOPS {
+                    let label = format!("{:.0}%", t * 100.0);
+                    entries.push((label, inferno(t)));
+                }
+            }
+            ModRender::Family => {
+                for (family, count) in most_common(world.iter().map(|(_, cell)| cell.family)) {
+                    let label = format!("{} ({})", family, count);
+                    entries.push((label, categorical(family as u64)));
+                }
+            }
+            ModRender::Genotype => {
+                let hashes = world.iter().map(|(_, cell)| cell.genome.genotype_hash());
+                for (hash, count) in most_common(hashes) {
+                    let label = format!("{:08x} ({})", hash >> 32, count);
+                    entries.push((label, categorical(hash)));
+                }
+            }
+            ModRender::Gene => {
+                for (kind, name) in Gene::KINDS.iter().enumerate() {
+                    entries.push((name.to_string(), qualitative(kind, Gene::VARIANT_COUNT)));
+                }
+                entries.push(("newborn".to_string(), NO_GENE));
+            }
+        }
+
+        let title = match self {
+            ModRender::Default => "cell colour",
+            ModRender::Energy => "energy",
+            ModRender::Toxin => "toxin",
+            ModRender::Health => "health",
+            ModRender::Family => "most common families",
+            ModRender::Lifetime => "lifetime / max lifetime",
+            ModRender::Genotype => "most common genotypes",
+            ModRender::Gene => "last gene",
+            ModRender::Step => "step / genome length",
+        };
+        Legend { title, entries }
+    }
+}
+
+impl fmt::Display for ModRender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ModRender::Default => "default",
+            ModRender::Energy => "energy",
+            ModRender::Toxin => "toxin",
+            ModRender::Health => "health",
+            ModRender::Family => "family",
+            ModRender::Lifetime => "lifetime",
+            ModRender::Genotype => "genotype",
+            ModRender::Gene => "gene",
+            ModRender::Step => "step",
+        })
+    }
+}
+
+impl FromStr for ModRender {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.to_string() == s)
+            .ok_or_else(|| format!("unknown render mode {}", s))
+    }
+}
+
+/// Colours of a render mode with their meaning
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Legend {
+    pub title: &'static str,
+    /// Label and colour, from the lowest value or the most common category
+    pub entries: Vec<(String, Rgb)>,
+}
+
+/// Maps a quantity to `0..=1`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Scale {
+    /// Logarithmic between `min` and `max`
+    Log { min: f32, max: f32 },
+    /// `x / (x + half)`, for the quantities without an upper bound
+    Saturating { half: f32 },
+}
+
+impl Scale {
+    fn normalize(&self, x: f32) -> f32 {
+        match *self {
+            Scale::Log { min, max } => (x.max(min) / min).ln() / (max / min).ln(),
+            Scale::Saturating { half } => {
+                let x = x.max(0.0);
+                x / (x + half)
+            }
+        }
+    }
+
+    /// Inverse of `normalize`
+    fn value(&self, t: f32) -> f32 {
+        match *self {
+            Scale::Log { min, max } => min * (max / min).powf(t),
+            Scale::Saturating { half } => half * t / (1.0 - t),
+        }
+    }
+}
+
+/// The black end of the map is lost on the background
+#[inline]
+fn inferno(t: f32) -> Rgb {
+    Colormap::Inferno.sample(0.15 + 0.85 * t)
+}
+
+/// Up to `LEGEND_TOP` values by count, ties broken by the value
+fn most_common<T: Copy + Ord + std::hash::Hash>(
+    values: impl Iterator<Item = T>,
+) -> Vec<(T, usize)> {
+    let mut counts: HashMap<T, usize> = HashMap::new();
+    for value in values {
+        *counts.entry(value).or_default() += 1;
+    }
+    let mut counts: Vec<(T, usize)> = counts.into_iter().collect();
+    counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    counts.truncate(LEGEND_TOP);
+    counts
+}
+
+/// RGB image, 3 bytes per pixel, rows from the top
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Frame {
+    /// Panics if the frame does not fit in memory, see `byte_len`
+    pub fn new(width: u32, height: u32, color: Rgb) -> Self {
+        let len = Self::byte_len(width, height).expect("frame too large");
+        let pixels = [color.0, color.1, color.2].repeat(len / 3);
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Bytes of the pixels, `None` if they do not fit in memory
+    pub fn byte_len(width: u32, height: u32) -> Option<usize> {
+        (width as usize)
+            .checked_mul(height as usize)?
+            .checked_mul(3)
+            .filter(|len| *len <= isize::MAX as usize)
+    }
+
+    /// Size of the frame of `world` at `scale`, `None` if it is too large
+    pub fn render_size(world: &World, scale: u32) -> Option<(u32, u32)> {
+        let scale = scale.max(1);
+        let width = (world.width().max(0) as u32).checked_mul(scale)?;
+        let height = (world.height().max(0) as u32).checked_mul(scale)?;
+        Self::byte_len(width, height).map(|_| (width, height))
+    }
+
+    /// Draws every cell of `world` as a `scale` x `scale` square, like `AppSdl`.
+    /// Fails if the frame is too large, see `render_size`
+    pub fn render(world: &World, mode: ModRender, scale: u32) -> io::Result<Self> {
+        let scale = scale.max(1);
+        let (width, height) = Self::render_size(world, scale).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("frame at scale {} is too large", scale),
+            )
+        })?;
+        let mut frame = Self::new(width, height, BACKGROUND);
+
+        for pos in world.walls() {
+            let (x, y) = (pos.x() as u32 * scale, pos.y() as u32 * scale);
+            frame.fill_rect(x, y, scale, scale, WALL);
+        }
+        let config = &world.config().cell;
+        for (pos, cell) in world.iter() {
+            let (x, y) = (pos.x().max(0) as u32 * scale, pos.y().max(0) as u32 * scale);
+            frame.fill_rect(x, y, scale, scale, mode.color(cell, config));
+        }
+
+        Ok(frame)
+    }
+
+    #[inline(always)]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[inline(always)]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[inline(always)]
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    pub fn set(&mut self, x: u32, y: u32, color: Rgb) {
+        if x < self.width && y < self.height {
+            let index = (y as usize * self.width as usize + x as usize) * 3;
+            self.pixels[index..index + 3].copy_from_slice(&[color.0, color.1, color.2]);
+        }
+    }
+
+    /// Clipped to the frame
+    pub fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: Rgb) {
+        for y in y..y.saturating_add(height).min(self.height) {
+            for x in x..x.saturating_add(width).min(self.width) {
+                self.set(x, y, color);
+            }
+        }
+    }
+
+    pub fn write_ppm<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.pixels)
+    }
+
+    pub fn write_png<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        match format {
+            ImageFormat::Png => self.write_png(&mut writer)?,
+            ImageFormat::Ppm => self.write_ppm(&mut writer)?,
+        }
+        writer.flush()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageFormat {
+    #[default]
+    Png,
+    Ppm,
+}
+
+impl ImageFormat {
+    #[inline]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "png" => Ok(ImageFormat::Png),
+            "ppm" => Ok(ImageFormat::Ppm),
+            _ => Err(format!("unknown image format {}", s)),
+        }
+    }
+}
+
+/// Saves a frame of the world into `dir` every `interval` ticks as
+/// `frame-<tick>.<ext>`, the errors are reported to stderr
+#[derive(Debug)]
+pub struct FrameExporter {
+    dir: PathBuf,
+    interval: u64,
+    mode: ModRender,
+    scale: u32,
+    format: ImageFormat,
+}
+
+impl FrameExporter {
+    /// Creates `dir` if needed
+    pub fn new<P: Into<PathBuf>>(dir: P, interval: u64) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            interval: interval.max(1),
+            mode: ModRender::Default,
+            scale: 2,
+            format: ImageFormat::Png,
+        })
+    }
+
+    pub fn with_mode(mut self, mode: ModRender) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn with_format(mut self, format: ImageFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn save(&self, world: &World) -> io::Result<PathBuf> {
+        let path = self.dir.join(format!(
+            "frame-{:012}.{}",
+            world.tick(),
+            self.format.extension()
+        ));
+        Frame::render(world, self.mode, self.scale)?.save(&path, self.format)?;
+        Ok(path)
+    }
+}
+
+impl Observer for FrameExporter {
+    fn on_tick(&mut self, world: &World) {
+        if world.tick().is_multiple_of(self.interval)
+            && let Err(e) = self.save(world)
+        {
+            eprintln!("failed to write frame: {}", e);
+        }
+    }
+
+    fn wants_events(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        math::Position,
+        testing::{self, TempDir},
+    };
+
+    fn pixel(frame: &Frame, x: u32, y: u32) -> Rgb {
+        let index = (y * frame.width() + x) as usize * 3;
+        let pixels = &frame.pixels()[index..index + 3];
+        (pixels[0], pixels[1], pixels[2])
+    }
+
+    /// One cell at (5, 6) and a wall at (7, 6) after an update
+    fn world() -> World {
+        let mut world = testing::one_cell(10, Position::new(5, 6));
+        world.set_wall(Position::new(7, 6), true);
+        world
+    }
+
+    #[test]
+    fn cells_and_walls_are_scaled_squares() {
+        let world = world();
+        let color = world.get(Position::new(5, 6)).unwrap().color;
+        let frame = Frame::render(&world, ModRender::Default, 3).unwrap();
+        assert_eq!(
+            (frame.width(), frame.height()),
+            (world.width() as u32 * 3, world.height() as u32 * 3)
+        );
+        for (dx, dy) in [(0, 0), (2, 2), (1, 2)] {
+            assert_eq!(pixel(&frame, 15 + dx, 18 + dy), color);
+            assert_eq!(pixel(&frame, 21 + dx, 18 + dy), WALL);
+        }
+        assert_eq!(pixel(&frame, 14, 18), BACKGROUND);
+        assert_eq!(pixel(&frame, 18, 18), BACKGROUND);
+        assert_eq!(pixel(&frame, 15, 21), BACKGROUND);
+    }
+
+    #[test]
+    fn mode_sets_the_cell_colour() {
+        let world = world();
+        let cell = world.get(Position::new(5, 6)).unwrap();
+        let frame = Frame::render(&world, ModRender::Energy, 1).unwrap();
+        assert_eq!(
+            pixel(&frame, 5, 6),
+            ModRender::Energy.color(cell, &world.config().cell)
+        );
+    }
+
+    #[test]
+    fn oversized_frames_are_refused() {
+        assert_eq!(Frame::byte_len(4, 2), Some(24));
+        assert_eq!(Frame::byte_len(u32::MAX, u32::MAX), None);
+        let world = world();
+        assert!(Frame::render_size(&world, u32::MAX).is_none());
+        let e = Frame::render(&world, ModRender::Default, u32::MAX).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn ppm_and_png_encodings() {
+        let mut frame = Frame::new(2, 1, BACKGROUND);
+        frame.set(1, 0, (1, 2, 3));
+        // outside of the frame
+        frame.set(2, 0, WALL);
+
+        let mut ppm = Vec::new();
+        frame.write_ppm(&mut ppm).unwrap();
+        assert_eq!(
+            ppm,
+            [&b"P6\n2 1\n255\n"[..], &[25, 25, 30, 1, 2, 3]].concat()
+        );
+
+        let mut png = Vec::new();
+        frame.write_png(&mut png).unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+
+        assert_eq!("ppm".parse(), Ok(ImageFormat::Ppm));
+        assert!("jpg".parse::<ImageFormat>().is_err());
+    }
+
+    #[test]
+    fn exporter_names_the_frames_by_tick() {
+        let dir = TempDir::new("frames");
+        let mut world = world();
+        let exporter = FrameExporter::new(&dir.0, 2)
+            .unwrap()
+            .with_format(ImageFormat::Ppm);
+        world.add_observer(Box::new(exporter));
+        for _ in 0..4 {
+            world.update();
+        }
+
+        let mut names: Vec<String> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, ["frame-000000000002.ppm", "frame-000000000004.ppm"]);
+    }
+
+    #[test]
+    fn modes_cycle_and_parse() {
+        let mut mode = ModRender::Default;
+        for expected in ModRender::ALL.iter().skip(1) {
+            mode = mode.next();
+            assert_eq!(mode, *expected);
+        }
+        assert_eq!(mode.next(), ModRender::Default);
+        for mode in ModRender::ALL {
+            assert_eq!(mode.to_string().parse(), Ok(mode));
+        }
+        assert!("age".parse::<ModRender>().is_err());
+    }
+
+    #[test]
+    fn gradients_follow_the_value() {
+        let world = world();
+        let config = &world.config().cell;
+        let mut cell = *world.get(Position::new(5, 6)).unwrap();
+
+        cell.energy = config.min_energy;
+        assert_eq!(
+            ModRender::Energy.color(&cell, config),
+            Colormap::Viridis.sample(0.0)
+        );
+        cell.energy = config.max_energy;
+        assert_eq!(
+            ModRender::Energy.color(&cell, config),
+            Colormap::Viridis.sample(1.0)
+        );
+        cell.toxin = 1.0;
+        assert_eq!(
+            ModRender::Toxin.color(&cell, config),
+            Colormap::Viridis.sample(0.5)
+        );
+
+        cell.lifetime = 0;
+        assert_eq!(ModRender::Lifetime.color(&cell, config), inferno(0.0));
+        cell.lifetime = cell.max_lifetime;
+        assert_eq!(ModRender::Lifetime.color(&cell, config), inferno(1.0));
+        cell.genome.step = 0;
+        assert_eq!(ModRender::Step.color(&cell, config), inferno(0.0));
+    }
+
+    #[test]
+    fn categories_have_stable_colours() {
+        let world = world();
+        let config = &world.config().cell;
+        let mut cell = *world.get(Position::new(5, 6)).unwrap();
+        let family = ModRender::Family.color(&cell, config);
+        assert_eq!(family, categorical(cell.family as u64));
+        cell.family += 1;
+        assert_ne!(ModRender::Family.color(&cell, config), family);
+
+        cell.last_gene = None;
+        assert_eq!(ModRender::Gene.color(&cell, config), NO_GENE);
+        cell.last_gene = Some(Gene::Stop);
+        assert_eq!(
+            ModRender::Gene.color(&cell, config),
+            qualitative(Gene::Stop.kind(), Gene::VARIANT_COUNT)
+        );
+        assert_eq!(ModRender::Default.color(&cell, config), cell.color);
+    }
+
+    #[test]
+    fn scales_are_inverted_by_the_legend() {
+        let scale = Scale::Log {
+            min: 0.5,
+            max: 50.0,
+        };
+        for t in LEGEND_STOPS {
+            assert!((scale.normalize(scale.value(t)) - t).abs() < 1e-5);
+        }
+        assert_eq!(scale.normalize(0.0), 0.0);
+        let scale = Scale::Saturating { half: 2.0 };
+        assert_eq!(scale.normalize(2.0), 0.5);
+        assert_eq!(scale.normalize(-1.0), 0.0);
+        assert!(scale.value(1.0).is_infinite());
+    }
+
+    #[test]
+    fn legends_of_the_modes() {
+        let mut world = testing::populated(testing::config(17), 6);
+        world.update();
+
+        assert!(ModRender::Default.legend(&world).entries.is_empty());
+        let energy = ModRender::Energy.legend(&world);
+        assert_eq!(energy.title, "energy");
+        assert_eq!(energy.entries.len(), LEGEND_STOPS.len());
+        assert_eq!(
+            ModRender::Toxin.legend(&world).entries.last().unwrap().0,
+            "inf"
+        );
+        let genes = ModRender::Gene.legend(&world);
+        assert_eq!(genes.entries.len(), Gene::VARIANT_COUNT + 1);
+
+        // the most common families first, the ties by the value
+        let families = ModRender::Family.legend(&world);
+        assert!(!families.entries.is_empty() && families.entries.len() <= LEGEND_TOP);
+        assert_eq!(
+            most_common([3, 1, 3, 2, 1, 3].into_iter()),
+            [(3, 3), (1, 2), (2, 1)]
+        );
+    }
+}
@@ -0,0 +1,453 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell::{Cell, CellId},
+    etc::{decode, encode},
+    genome::Gene,
+    math::Position,
+    observer::Observer,
+    world::{World, WorldSnapshot},
+};
+
+const MAGIC: &[u8; 8] = b"EVOREPL4";
+/// kind: u8, tick: u64, length of the payload: u32
+const HEADER_LEN: u64 = 13;
+const KEYFRAME: u8 = 0;
+const TICK: u8 = 1;
+
+/// What a cell did during one update
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Action {
+    pub id: CellId,
+    pub from: Position,
+    pub to: Position,
+    pub gene: Gene,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TickRecord {
+    actions: Vec<Action>,
+}
+
+/// Writes the replay of a `World`: a keyframe (`WorldSnapshot`) every
+/// `keyframe_interval` ticks and the actions of the cells on every tick.
+///
+/// The edits of the world between the updates need a `keyframe` from the
+/// owner, and `finish` flushes the log.
+pub struct ReplayRecorder<W: Write> {
+    inner: W,
+    keyframe_interval: u64,
+    record_actions: bool,
+    actions: Vec<Action>,
+    payload: Vec<u8>,
+    error: Option<io::Error>,
+}
+
+impl ReplayRecorder<BufWriter<File>> {
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        world: &World,
+        keyframe_interval: u64,
+    ) -> io::Result<Self> {
+        Self::new(
+            BufWriter::new(File::create(path)?),
+            world,
+            keyframe_interval,
+        )
+    }
+}
+
+impl<W: Write> ReplayRecorder<W> {
+    /// Writes the initial keyframe, `world` must not be in the middle of an update
+    pub fn new(mut inner: W, world: &World, keyframe_interval: u64) -> io::Result<Self> {
+        inner.write_all(MAGIC)?;
+        let mut recorder = Self {
+            inner,
+            keyframe_interval: keyframe_interval.max(1),
+            record_actions: true,
+            actions: Vec::new(),
+            payload: Vec::new(),
+            error: None,
+        };
+        recorder.write_keyframe(world)?;
+        Ok(recorder)
+    }
+
+    /// Actions are the bulk of the log, a replay without them still
+    /// reconstructs every tick
+    pub fn set_actions_recording(&mut self, enabled: bool) {
+        self.record_actions = enabled;
+    }
+
+    /// Writes a keyframe out of schedule.
+    ///
+    /// Edits of the world made outside of `World::update` are not
+    /// re-simulated by `ReplayPlayer`, call it after every edit.
+    pub fn keyframe(&mut self, world: &World) {
+        let result = self.write_keyframe(world);
+        self.keep_error(result);
+    }
+
+    /// Flushes the log, returns the first error met while recording
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn write_keyframe(&mut self, world: &World) -> io::Result<()> {
+        self.write_record(KEYFRAME, world.tick(), &world.snapshot())
+    }
+
+    fn write_record<T: Serialize>(&mut self, kind: u8, tick: u64, value: &T) -> io::Result<()> {
+        self.payload.clear();
+        encode(&mut self.payload, value)?;
+        let len = u32::try_from(self.payload.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "replay record is too big"))?;
+
+        self.inner.write_all(&[kind])?;
+        self.inner.write_all(&tick.to_le_bytes())?;
+        self.inner.write_all(&len.to_le_bytes())?;
+        self.inner.write_all(&self.payload)
+    }
+
+    fn keep_error(&mut self, result: io::Result<()>) {
+        if let Err(e) = result
+            && self.error.is_none()
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+impl<W: Write + Send> Observer for ReplayRecorder<W> {
+    fn on_action(&mut self, _tick: u64, cell: &Cell, from: Position, to: Position, gene: Gene) {
+        if self.record_actions {
+            self.actions.push(Action {
+                id: cell.id,
+                from,
+                to,
+                gene,
+            });
+        }
+    }
+
+    fn on_tick(&mut self, world: &World) {
+        if self.error.is_some() {
+            self.actions.clear();
+            return;
+        }
+
+        let record = TickRecord {
+            actions: std::mem::take(&mut self.actions),
+        };
+        let mut result = self.write_record(TICK, world.tick() - 1, &record);
+        if result.is_ok() && world.tick().is_multiple_of(self.keyframe_interval) {
+            result = self.write_keyframe(world);
+        }
+        self.keep_error(result);
+
+        self.actions = record.actions;
+        self.actions.clear();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    tick: u64,
+    offset: u64,
+    len: u32,
+}
+
+/// Reconstructs the ticks of a replay written by `ReplayRecorder`.
+///
+/// A tick is re-simulated from the nearest keyframe before it, so seeking
+/// backwards costs at most `keyframe_interval` updates. A log cut by a crash
+/// is played up to its last complete record.
+pub struct ReplayPlayer<R: Read + Seek> {
+    inner: R,
+    /// Sorted by tick, then by the position in the log
+    keyframes: Vec<Record>,
+    ticks: Vec<Record>,
+    world: World,
+}
+
+impl ReplayPlayer<BufReader<File>> {
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> ReplayPlayer<R> {
+    /// Indexes the log and loads the first keyframe
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let end = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(0))?;
+        let mut magic = [0; MAGIC.len()];
+        inner.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a replay"));
+        }
+
+        let (mut keyframes, mut ticks) = (Vec::new(), Vec::new());
+        let mut offset = MAGIC.len() as u64;
+        while offset + HEADER_LEN <= end {
+            let mut header = [0; HEADER_LEN as usize];
+            inner.read_exact(&mut header)?;
+            let record = Record {
+                tick: u64::from_le_bytes(header[1..9].try_into().unwrap()),
+                offset: offset + HEADER_LEN,
+                len: u32::from_le_bytes(header[9..13].try_into().unwrap()),
+            };
+            if record.offset + record.len as u64 > end {
+                break;
+            }
+
+            match header[0] {
+                KEYFRAME => keyframes.push(record),
+                TICK => ticks.push(record),
+                _ => return Err(invalid_data("unknown replay record")),
+            }
+            offset = record.offset + record.len as u64;
+            inner.seek(SeekFrom::Start(offset))?;
+        }
+
+        let first = *keyframes
+            .first()
+            .ok_or_else(|| invalid_data("replay has no keyframe"))?;
+        let snapshot: WorldSnapshot = read_record(&mut inner, first)?;
+        let mut player = Self {
+            inner,
+            keyframes,
+            ticks,
+            world: World::from_snapshot(snapshot)?,
+        };
+        player.load_latest_keyframe()?;
+
+        Ok(player)
+    }
+
+    #[inline(always)]
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    #[inline(always)]
+    pub fn tick(&self) -> u64 {
+        self.world.tick()
+    }
+
+    pub fn first_tick(&self) -> u64 {
+        self.keyframes[0].tick
+    }
+
+    pub fn last_tick(&self) -> u64 {
+        let keyframe = self.keyframes[self.keyframes.len() - 1].tick;
+        self.ticks
+            .last()
+            .map_or(keyframe, |record| keyframe.max(record.tick + 1))
+    }
+
+    /// Reconstructs `tick`, clamped to the recorded range
+    pub fn seek(&mut self, tick: u64) -> io::Result<()> {
+        let target = tick.clamp(self.first_tick(), self.last_tick());
+        let index = self
+            .keyframes
+            .partition_point(|record| record.tick <= target)
+            - 1;
+        let keyframe = self.keyframes[index];
+
+        let current = self.world.tick();
+        if current < keyframe.tick || current > target {
+            self.world = World::from_snapshot(read_record(&mut self.inner, keyframe)?)?;
+        }
+        while self.world.tick() < target {
+            self.world.update();
+            self.load_latest_keyframe()?;
+        }
+
+        Ok(())
+    }
+
+    /// false - the end of the replay
+    pub fn step_forward(&mut self) -> io::Result<bool> {
+        if self.tick() >= self.last_tick() {
+            return Ok(false);
+        }
+        self.seek(self.tick() + 1)?;
+        Ok(true)
+    }
+
+    /// false - the start of the replay
+    pub fn step_back(&mut self) -> io::Result<bool> {
+        if self.tick() <= self.first_tick() {
+            return Ok(false);
+        }
+        self.seek(self.tick() - 1)?;
+        Ok(true)
+    }
+
+    /// Actions taken by the update from `tick` to `tick + 1`,
+    /// empty if they were not recorded
+    pub fn actions(&mut self, tick: u64) -> io::Result<Vec<Action>> {
+        match self.ticks.binary_search_by_key(&tick, |record| record.tick) {
+            Ok(index) => {
+                let record: TickRecord = read_record(&mut self.inner, self.ticks[index])?;
+                Ok(record.actions)
+            }
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    /// A keyframe written after an edit of the world replaces the simulated state
+    fn load_latest_keyframe(&mut self) -> io::Result<()> {
+        let tick = self.world.tick();
+        let index = self.keyframes.partition_point(|record| record.tick <= tick);
+        if index > 0 && self.keyframes[index - 1].tick == tick {
+            let snapshot = read_record(&mut self.inner, self.keyframes[index - 1])?;
+            self.world = World::from_snapshot(snapshot)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn read_record<R, T>(inner: &mut R, record: Record) -> io::Result<T>
+where
+    R: Read + Seek,
+    T: for<'de> Deserialize<'de>,
+{
+    inner.seek(SeekFrom::Start(record.offset))?;
+    let mut payload = vec![0; record.len as usize];
+    inner.read_exact(&mut payload)?;
+    decode(payload.as_slice())
+}
+
+#[inline(always)]
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Cursor,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::testing::{self, state};
+
+    const TICKS: u64 = 60;
+    const KEYFRAME_INTERVAL: u64 = 16;
+
+    fn world() -> World {
+        testing::populated(testing::config(5), 8)
+    }
+
+    /// The log of `TICKS` updates and the state after each of them
+    fn record(edit_at: Option<u64>) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let mut world = world();
+        let recorder = ReplayRecorder::new(Vec::new(), &world, KEYFRAME_INTERVAL).unwrap();
+        let recorder = Arc::new(Mutex::new(recorder));
+        world.add_observer(Box::new(recorder.clone()));
+
+        let mut states = vec![state(&world)];
+        for tick in 1..=TICKS {
+            world.update();
+            if edit_at == Some(tick) {
+                let cell = world.new_cell();
+                world.spawn(Position::new(30, 30), cell);
+                recorder.lock().unwrap().keyframe(&world);
+            }
+            states.push(state(&world));
+        }
+
+        let mut recorder = recorder.lock().unwrap();
+        recorder.finish().unwrap();
+        (std::mem::take(&mut recorder.inner), states)
+    }
+
+    #[test]
+    fn seek_reconstructs_every_tick() {
+        let (log, states) = record(None);
+        let mut player = ReplayPlayer::new(Cursor::new(log)).unwrap();
+        assert_eq!(player.first_tick(), 0);
+        assert_eq!(player.last_tick(), TICKS);
+
+        // forwards, backwards across the keyframes and then at random
+        let targets = (0..=TICKS)
+            .chain((0..=TICKS).rev())
+            .chain([50, 3, 33, 17, 16, 60, 0]);
+        for tick in targets {
+            player.seek(tick).unwrap();
+            assert_eq!(player.tick(), tick);
+            assert_eq!(
+                state(player.world()),
+                states[tick as usize],
+                "tick {}",
+                tick
+            );
+        }
+
+        player.seek(TICKS + 100).unwrap();
+        assert_eq!(player.tick(), TICKS);
+        assert!(!player.step_forward().unwrap());
+        assert!(player.step_back().unwrap());
+        assert_eq!(player.tick(), TICKS - 1);
+    }
+
+    #[test]
+    fn keyframe_keeps_the_edits() {
+        let (log, states) = record(Some(21));
+        let mut player = ReplayPlayer::new(Cursor::new(log)).unwrap();
+        for tick in [25, 21, 20, 40] {
+            player.seek(tick).unwrap();
+            assert_eq!(
+                state(player.world()),
+                states[tick as usize],
+                "tick {}",
+                tick
+            );
+        }
+    }
+
+    #[test]
+    fn actions_are_recorded() {
+        let (log, _) = record(None);
+        let mut player = ReplayPlayer::new(Cursor::new(log)).unwrap();
+        // the seeded cells are updated from the second tick on
+        assert!(player.actions(0).unwrap().is_empty());
+        assert!(!player.actions(1).unwrap().is_empty());
+        assert!(player.actions(TICKS).unwrap().is_empty());
+    }
+
+    #[test]
+    fn cut_log_is_played_to_the_last_record() {
+        let (mut log, states) = record(None);
+        log.truncate(log.len() - 3);
+        let mut player = ReplayPlayer::new(Cursor::new(log)).unwrap();
+        assert_eq!(player.last_tick(), TICKS - 1);
+        player.seek(TICKS).unwrap();
+        assert_eq!(state(player.world()), states[TICKS as usize - 1]);
+    }
+
+    #[test]
+    fn other_files_are_rejected() {
+        let e = ReplayPlayer::new(Cursor::new(b"EVOCKPT1".to_vec()))
+            .err()
+            .unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,384 @@
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use crate::{
+    cell::{Cell, DeathCause},
+    genome::{Gene, analysis::GenomeAnalysis},
+    observer::Observer,
+    world::{Counters, World},
+};
+
+/// Mean and variance of a population value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Moments {
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl Moments {
+    /// Population variance by the Welford's algorithm, stable for the
+    /// values far from zero
+    pub fn from_values<I: IntoIterator<Item = f64>>(values: I) -> Self {
+        let (mut n, mut mean, mut m2) = (0usize, 0.0, 0.0);
+        for value in values {
+            n += 1;
+            let delta = value - mean;
+            mean += delta / n as f64;
+            m2 += delta * (value - mean);
+        }
+
+        if n == 0 {
+            return Self::default();
+        }
+
+        Self {
+            mean,
+            variance: m2 / n as f64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub tick: u64,
+    pub population: usize,
+    /// Births since the previous sample
+    pub births: u64,
+    pub mutations: u64,
+    pub mutated_loci: u64,
+    pub junk_mutated_loci: u64,
+    /// Deaths since the previous sample, indexed by `DeathCause as usize`
+    pub deaths: [u64; DeathCause::VARIANT_COUNT],
+    pub energy: Moments,
+    pub health: Moments,
+    pub toxin: Moments,
+    pub lifetime: Moments,
+    pub mutation_rate: Moments,
+    /// Share of the dead genes in a genome
+    pub junk: Moments,
+    /// Share of every gene kind over all loci, indexed by `Gene::kind`
+    pub gene_frequencies: [f64; Gene::VARIANT_COUNT],
+    pub families: usize,
+}
+
+impl Sample {
+    /// Counters are taken relative to `since`
+    pub fn take(world: &World, since: &Counters) -> Self {
+        let counters = world.counters();
+        let mut deaths = [0; DeathCause::VARIANT_COUNT];
+        deaths
+            .iter_mut()
+            .zip(counters.deaths.iter().zip(since.deaths.iter()))
+            .for_each(|(d, (now, before))| *d = now - before);
+
+        let families: HashSet<_> = world.iter().map(|(_, cell)| cell.family).collect();
+
+        Self {
+            tick: world.tick(),
+            population: world.count_cells(),
+            births: counters.births - since.births,
+            mutations: counters.mutations - since.mutations,
+            mutated_loci: counters.mutated_loci - since.mutated_loci,
+            junk_mutated_loci: counters.junk_mutated_loci - since.junk_mutated_loci,
+            deaths,
+            energy: moments(world, |cell| cell.energy as f64),
+            health: moments(world, |cell| cell.health as f64),
+            toxin: moments(world, |cell| cell.toxin as f64),
+            lifetime: moments(world, |cell| cell.lifetime as f64),
+            mutation_rate: moments(world, |cell| cell.mutation.cell),
+            junk: moments(world, |cell| {
+                GenomeAnalysis::new(&cell.genome).junk_fraction()
+            }),
+            gene_frequencies: gene_frequencies(world),
+            families: families.len(),
+        }
+    }
+
+    pub fn write_csv_header<W: Write>(mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "tick,population,births,mutations,mutated_loci,junk_mutated_loci"
+        )?;
+        for cause in DeathCause::ALL {
+            write!(writer, ",deaths_{}", cause.name())?;
+        }
+        for name in [
+            "energy",
+            "health",
+            "toxin",
+            "lifetime",
+            "mutation_rate",
+            "junk",
+        ] {
+            write!(writer, ",{}_mean,{}_var", name, name)?;
+        }
+        for kind in Gene::KINDS {
+            write!(writer, ",gene_{}", kind)?;
+        }
+        writeln!(writer, ",families")
+    }
+
+    pub fn write_csv_row<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        write!(
+            writer,
+            "{},{},{},{},{},{}",
+            self.tick,
+            self.population,
+            self.births,
+            self.mutations,
+            self.mutated_loci,
+            self.junk_mutated_loci
+        )?;
+        for deaths in self.deaths {
+            write!(writer, ",{}", deaths)?;
+        }
+        for moments in [
+            self.energy,
+            self.health,
+            self.toxin,
+            self.lifetime,
+            self.mutation_rate,
+            self.junk,
+        ] {
+            write!(writer, ",{},{}", moments.mean, moments.variance)?;
+        }
+        for frequency in self.gene_frequencies {
+            write!(writer, ",{}", frequency)?;
+        }
+        writeln!(writer, ",{}", self.families)
+    }
+}
+
+fn moments<F: Fn(&Cell) -> f64>(world: &World, f: F) -> Moments {
+    Moments::from_values(world.iter().map(|(_, cell)| f(cell)))
+}
+
+/// Share of every gene kind over all loci of the population, indexed by `Gene::kind`
+pub fn gene_frequencies(world: &World) -> [f64; Gene::VARIANT_COUNT] {
+    let mut genes = [0u64; Gene::VARIANT_COUNT];
+    for (_, cell) in world.iter() {
+        cell.genome
+            .genes()
+            .iter()
+            .for_each(|gene| genes[gene.kind()] += 1);
+    }
+    let total_genes = genes.iter().sum::<u64>().max(1) as f64;
+    genes.map(|count| count as f64 / total_genes)
+}
+
+/// Takes a `Sample` every `interval` ticks.
+///
+/// Call `record` after every `World::update`. The first sample counts the
+/// events since `new`.
+#[derive(Debug)]
+pub struct StatsCollector {
+    interval: u64,
+    last: Counters,
+    last_tick: Option<u64>,
+    samples: Vec<Sample>,
+}
+
+impl StatsCollector {
+    pub fn new(world: &World, interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            last: *world.counters(),
+            last_tick: None,
+            samples: Vec::new(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    /// true - a sample was taken
+    pub fn record(&mut self, world: &World) -> bool {
+        let tick = world.tick();
+        if !tick.is_multiple_of(self.interval) || self.last_tick == Some(tick) {
+            return false;
+        }
+
+        self.samples.push(Sample::take(world, &self.last));
+        self.last = *world.counters();
+        self.last_tick = Some(tick);
+
+        true
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn last_sample(&self) -> Option<&Sample> {
+        self.samples.last()
+    }
+
+    /// Takes the samples recorded since the last call
+    pub fn drain(&mut self) -> std::vec::Drain<'_, Sample> {
+        self.samples.drain(..)
+    }
+
+    pub fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        Sample::write_csv_header(&mut writer)?;
+        for sample in &self.samples {
+            sample.write_csv_row(&mut writer)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Appends a CSV row every `interval` ticks as an observer of a `World`.
+///
+/// `finish` flushes the rows and returns the first error met while recording.
+pub struct StatsWriter<W: Write> {
+    collector: StatsCollector,
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> StatsWriter<W> {
+    /// Writes the header, the rows count the events since `new`
+    pub fn new(mut inner: W, world: &World, interval: u64) -> io::Result<Self> {
+        Sample::write_csv_header(&mut inner)?;
+        Ok(Self {
+            collector: StatsCollector::new(world, interval),
+            inner,
+            error: None,
+        })
+    }
+
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.inner.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write + Send> Observer for StatsWriter<W> {
+    fn on_tick(&mut self, world: &World) {
+        if !self.collector.record(world) {
+            return;
+        }
+
+        let inner = &mut self.inner;
+        let result = self
+            .collector
+            .drain()
+            .try_for_each(|sample| sample.write_csv_row(&mut *inner));
+        if let Err(e) = result
+            && self.error.is_none()
+        {
+            self.error = Some(e);
+        }
+    }
+
+    fn wants_events(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{math::Position, testing};
+
+    #[test]
+    fn moments_of_known_values() {
+        let moments = Moments::from_values([1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(
+            moments,
+            Moments {
+                mean: 2.5,
+                variance: 1.25
+            }
+        );
+        assert_eq!(Moments::from_values([]), Moments::default());
+
+        // the naive sum of the squares loses the variance here
+        let moments = Moments::from_values([1.0, 2.0, 3.0, 4.0].map(|value| 1e9 + value));
+        assert!((moments.variance - 1.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn sample_counts_births_and_deaths() {
+        let mut world = World::new(testing::config(1));
+        let since = *world.counters();
+        for x in [10, 20, 30] {
+            let cell = world.new_cell();
+            assert!(world.spawn(Position::new(x, 20), cell));
+        }
+        assert!(world.del(Position::new(20, 20)));
+
+        let sample = Sample::take(&world, &since);
+        assert_eq!(sample.births, 3);
+        assert_eq!(sample.deaths[DeathCause::Removed as usize], 1);
+        assert_eq!(sample.deaths.iter().sum::<u64>(), 1);
+        assert_eq!(sample.mutations, 0);
+    }
+
+    #[test]
+    fn collector_counts_from_its_start() {
+        let mut world = testing::populated(testing::config(2), 6);
+        for _ in 0..10 {
+            world.update();
+        }
+        let start = *world.counters();
+        let mut collector = StatsCollector::new(&world, 5);
+        for _ in 0..10 {
+            world.update();
+            collector.record(&world);
+        }
+        // a second call at the same tick takes no sample
+        assert!(!collector.record(&world));
+
+        let samples = collector.samples();
+        assert_eq!(samples.len(), 2);
+        assert_eq!(samples[0].tick, 15);
+        assert_eq!(samples[1].population, world.count_cells());
+        let births: u64 = samples.iter().map(|sample| sample.births).sum();
+        assert_eq!(births, world.counters().births - start.births);
+        assert_eq!(
+            samples
+                .iter()
+                .map(|sample| sample.deaths)
+                .fold(0, |sum, deaths| { sum + deaths.iter().sum::<u64>() }),
+            world.counters().total_deaths() - start.total_deaths()
+        );
+    }
+
+    #[test]
+    fn writer_appends_a_row_per_sample() {
+        let mut world = testing::populated(testing::config(3), 6);
+        let writer = StatsWriter::new(Vec::new(), &world, 4).unwrap();
+        let writer = Arc::new(Mutex::new(writer));
+        world.add_observer(Box::new(writer.clone()));
+        for _ in 0..12 {
+            world.update();
+        }
+        drop(world);
+
+        let mut writer = Arc::into_inner(writer).unwrap().into_inner().unwrap();
+        writer.finish().unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("tick,population,births"));
+        let columns = lines[0].split(',').count();
+        for (line, tick) in lines[1..].iter().zip([4, 8, 12]) {
+            assert_eq!(line.split(',').count(), columns);
+            assert!(line.starts_with(&format!("{},", tick)));
+        }
+    }
+}
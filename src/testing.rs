@@ -0,0 +1,65 @@
+//! Fixtures of the unit tests
+
+use std::{fs, path::PathBuf};
+
+use crate::{config::SimConfig, math::Position, world::World};
+
+/// Directory removed when dropped
+pub(crate) struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("evocell-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        Self(dir)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Small dish with a fixed seed
+pub(crate) fn config(seed: u64) -> SimConfig {
+    SimConfig {
+        seed: Some(seed),
+        radius_petri_dish: 20,
+        width: 60,
+        ..SimConfig::default()
+    }
+}
+
+/// World with a seeded cell every `step` positions of a grid
+pub(crate) fn populated(config: SimConfig, step: usize) -> World {
+    let mut world = World::new(config);
+    populate(&mut world, step);
+    world
+}
+
+/// Seeds a cell every `step` positions of a grid
+pub(crate) fn populate(world: &mut World, step: usize) {
+    for x in (2..world.width()).step_by(step) {
+        for y in (2..world.height()).step_by(step) {
+            let cell = world.new_cell();
+            world.spawn(Position::new(x, y), cell);
+        }
+    }
+}
+
+/// World of one cell at `pos` after an update
+pub(crate) fn one_cell(seed: u64, pos: Position) -> World {
+    let mut world = World::new(config(seed));
+    let cell = world.new_cell();
+    world.spawn(pos, cell);
+    world.update();
+    world
+}
+
+/// Encoded snapshot, equal for the worlds in the same state
+pub(crate) fn state(world: &World) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    world.snapshot().write_to(&mut bytes).unwrap();
+    bytes
+}
@@ -0,0 +1,288 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use crate::{
+    observer::Observer,
+    render::{Frame, ImageFormat, ModRender},
+    world::World,
+};
+
+/// Quality of the palette of a GIF frame, 1 - best and slowest, 30 - fastest
+const GIF_QUANTIZATION_SPEED: i32 = 10;
+const FRAME_PREFIX: &str = "frame-";
+
+enum Sink {
+    Gif {
+        writer: Option<BufWriter<File>>,
+        /// Created by the first frame, when the size is known
+        encoder: Option<gif::Encoder<BufWriter<File>>>,
+    },
+    Frames {
+        dir: PathBuf,
+        format: ImageFormat,
+        /// Index of the next image
+        next: usize,
+    },
+}
+
+/// Encodes a frame every `interval` ticks into an animated GIF
+/// or a directory of numbered images (`frame-000000.png`, ...).
+/// The numbering continues after the images already in the directory,
+/// e.g. of the run before a resume.
+///
+/// The GIF is only complete after `finish`, which also reports the errors
+/// of the frames written as an observer.
+pub struct Timelapse {
+    sink: Sink,
+    interval: u64,
+    mode: ModRender,
+    scale: u32,
+    /// Delay between the GIF frames, in hundredths of a second
+    delay: u16,
+    count_frames: usize,
+    error: Option<io::Error>,
+}
+
+impl Timelapse {
+    pub fn gif<P: Into<PathBuf>>(path: P, interval: u64) -> io::Result<Self> {
+        let writer = BufWriter::new(File::create(path.into())?);
+        Ok(Self::new(
+            Sink::Gif {
+                writer: Some(writer),
+                encoder: None,
+            },
+            interval,
+        ))
+    }
+
+    /// Creates `dir` if needed
+    pub fn frames<P: Into<PathBuf>>(
+        dir: P,
+        interval: u64,
+        format: ImageFormat,
+    ) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        let suffix = format!(".{}", format.extension());
+        let mut next = 0;
+        for entry in fs::read_dir(&dir)? {
+            let index = entry?
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(FRAME_PREFIX)?.strip_suffix(&suffix))
+                .and_then(|index| index.parse::<usize>().ok());
+            if let Some(index) = index {
+                next = next.max(index + 1);
+            }
+        }
+        Ok(Self::new(Sink::Frames { dir, format, next }, interval))
+    }
+
+    fn new(sink: Sink, interval: u64) -> Self {
+        Self {
+            sink,
+            interval: interval.max(1),
+            mode: ModRender::Default,
+            scale: 2,
+            delay: 10,
+            count_frames: 0,
+            error: None,
+        }
+    }
+
+    pub fn with_mode(mut self, mode: ModRender) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    /// Playback speed of a GIF, frames per second
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.delay = (100 / fps.clamp(1, 100)) as u16;
+        self
+    }
+
+    /// Frames added since the creation
+    #[inline(always)]
+    pub fn count_frames(&self) -> usize {
+        self.count_frames
+    }
+
+    pub fn add_frame(&mut self, world: &World) -> io::Result<()> {
+        let frame = Frame::render(world, self.mode, self.scale)?;
+        match &mut self.sink {
+            Sink::Gif { writer, encoder } => {
+                if encoder.is_none() {
+                    let writer = writer
+                        .take()
+                        .ok_or_else(|| io::Error::other("timelapse is finished"))?;
+                    let (width, height) = gif_size(&frame)?;
+                    let mut new =
+                        gif::Encoder::new(writer, width, height, &[]).map_err(io::Error::other)?;
+                    new.set_repeat(gif::Repeat::Infinite)
+                        .map_err(io::Error::other)?;
+                    *encoder = Some(new);
+                }
+
+                let (width, height) = gif_size(&frame)?;
+                let mut gif_frame = gif::Frame::from_rgb_speed(
+                    width,
+                    height,
+                    frame.pixels(),
+                    GIF_QUANTIZATION_SPEED,
+                );
+                gif_frame.delay = self.delay;
+                encoder
+                    .as_mut()
+                    .unwrap()
+                    .write_frame(&gif_frame)
+                    .map_err(io::Error::other)?;
+            }
+            Sink::Frames { dir, format, next } => {
+                let path = dir.join(format!(
+                    "{}{:06}.{}",
+                    FRAME_PREFIX,
+                    next,
+                    format.extension()
+                ));
+                frame.save(path, *format)?;
+                *next += 1;
+            }
+        }
+        self.count_frames += 1;
+
+        Ok(())
+    }
+
+    /// Completes the GIF, returns the first error met while recording
+    pub fn finish(&mut self) -> io::Result<()> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+
+        if let Sink::Gif { writer, encoder } = &mut self.sink {
+            if let Some(encoder) = encoder.take() {
+                encoder.into_inner()?.flush()?;
+            } else if let Some(mut writer) = writer.take() {
+                // no frames, still a valid file is better than an empty one
+                gif::Encoder::new(&mut writer, 1, 1, &[]).map_err(io::Error::other)?;
+                writer.flush()?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Observer for Timelapse {
+    fn on_tick(&mut self, world: &World) {
+        if self.error.is_none()
+            && world.tick().is_multiple_of(self.interval)
+            && let Err(e) = self.add_frame(world)
+        {
+            self.error = Some(e);
+        }
+    }
+
+    fn wants_events(&self) -> bool {
+        false
+    }
+}
+
+fn gif_size(frame: &Frame) -> io::Result<(u16, u16)> {
+    match (u16::try_from(frame.width()), u16::try_from(frame.height())) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "frame is too big for a GIF",
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::testing::{self, TempDir};
+
+    fn names(dir: &TempDir) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+
+    #[test]
+    fn frames_are_numbered_every_interval() {
+        let dir = TempDir::new("timelapse-frames");
+        let mut world = testing::populated(testing::config(11), 8);
+        let timelapse = Timelapse::frames(&dir.0, 3, ImageFormat::Ppm).unwrap();
+        let timelapse = Arc::new(Mutex::new(timelapse));
+        world.add_observer(Box::new(timelapse.clone()));
+        for _ in 0..7 {
+            world.update();
+        }
+
+        let mut timelapse = timelapse.lock().unwrap();
+        timelapse.finish().unwrap();
+        assert_eq!(timelapse.count_frames(), 2);
+        assert_eq!(names(&dir), ["frame-000000.ppm", "frame-000001.ppm"]);
+    }
+
+    #[test]
+    fn numbering_continues_after_a_resume() {
+        let dir = TempDir::new("timelapse-resume");
+        fs::create_dir_all(&dir.0).unwrap();
+        for name in ["frame-000004.ppm", "frame-000009.png", "notes.txt"] {
+            fs::write(dir.0.join(name), b"").unwrap();
+        }
+
+        let world = testing::populated(testing::config(12), 8);
+        let mut timelapse = Timelapse::frames(&dir.0, 1, ImageFormat::Ppm).unwrap();
+        timelapse.add_frame(&world).unwrap();
+        timelapse.add_frame(&world).unwrap();
+        assert_eq!(timelapse.count_frames(), 2);
+        assert_eq!(
+            names(&dir),
+            [
+                "frame-000004.ppm",
+                "frame-000005.ppm",
+                "frame-000006.ppm",
+                "frame-000009.png",
+                "notes.txt"
+            ]
+        );
+        // the existing frames are kept
+        assert!(fs::read(dir.0.join("frame-000004.ppm")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn gif_is_valid_with_or_without_frames() {
+        let dir = TempDir::new("timelapse-gif");
+        fs::create_dir_all(&dir.0).unwrap();
+        let world = testing::populated(testing::config(13), 8);
+
+        let path = dir.0.join("run.gif");
+        let mut timelapse = Timelapse::gif(&path, 1).unwrap().with_scale(1);
+        timelapse.add_frame(&world).unwrap();
+        timelapse.add_frame(&world).unwrap();
+        timelapse.finish().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!(bytes.last(), Some(&0x3b), "the trailer is written");
+
+        let empty = dir.0.join("empty.gif");
+        Timelapse::gif(&empty, 1).unwrap().finish().unwrap();
+        assert!(fs::read(&empty).unwrap().starts_with(b"GIF89a"));
+    }
+}
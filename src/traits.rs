@@ -1,14 +1,16 @@
 use rand::Rng;
 
+use crate::{etc::SimRng, mutation::MutationProfile};
+
 pub trait Mutable {
-    fn mutate(&mut self) -> bool;
+    fn mutate(&mut self, profile: &MutationProfile, rng: &mut SimRng) -> bool;
 }
 
 pub trait GetRandomVariant {
     const VARIANT_COUNT: usize;
 
-    fn get_rand_variant(self) -> Self;
-    fn gen_idx_variant() -> usize {
-        rand::thread_rng().gen_range(0..Self::VARIANT_COUNT)
+    fn get_rand_variant(self, rng: &mut SimRng) -> Self;
+    fn gen_idx_variant(rng: &mut SimRng) -> usize {
+        rng.gen_range(0..Self::VARIANT_COUNT)
     }
 }
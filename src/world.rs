@@ -1,30 +1,347 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    ops::AddAssign,
+    sync::{Arc, Mutex},
+};
 
-use crate::{cell::Cell, consts::RADIUS_PETRI_DISH, math::Position};
+use rand::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
 
-pub const WIDTH: i32 = 360;
-pub const HEIGHT: i32 = RADIUS_PETRI_DISH * 2;
+use crate::{
+    cell::{Cell, CellId, DeathCause, NO_PARENT},
+    config::{ConfigError, SimConfig},
+    consts::{GENOTYPE_PRUNE_INTERVAL, INTERACTION_RADIUS},
+    etc::{SimRng, decode, encode},
+    genome::{GenotypeHash, analysis::count_mutated_loci},
+    lineage::LineageEvent,
+    math::Position,
+    mutation::MutationProfile,
+    observer::{EventLog, Observer, ObserverId},
+    pool::WorkerPool,
+};
+
+/// Cumulative event counters of a `World`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Counters {
+    pub births: u64,
+    pub mutations: u64,
+    /// Loci changed by the mutations
+    pub mutated_loci: u64,
+    /// Mutated loci that were dead code in the parent
+    pub junk_mutated_loci: u64,
+    /// Indexed by `DeathCause as usize`
+    pub deaths: [u64; DeathCause::VARIANT_COUNT],
+}
+
+impl Counters {
+    #[inline]
+    pub fn deaths_by(&self, cause: DeathCause) -> u64 {
+        self.deaths[cause as usize]
+    }
+
+    pub fn total_deaths(&self) -> u64 {
+        self.deaths.iter().sum()
+    }
+
+    /// Share of the mutated loci that landed in junk DNA
+    pub fn junk_mutation_fraction(&self) -> f64 {
+        if self.mutated_loci == 0 {
+            return 0.0;
+        }
+        self.junk_mutated_loci as f64 / self.mutated_loci as f64
+    }
+}
+
+impl AddAssign for Counters {
+    fn add_assign(&mut self, other: Self) {
+        self.births += other.births;
+        self.mutations += other.mutations;
+        self.mutated_loci += other.mutated_loci;
+        self.junk_mutated_loci += other.junk_mutated_loci;
+        for (deaths, other) in self.deaths.iter_mut().zip(other.deaths) {
+            *deaths += other;
+        }
+    }
+}
+
+/// State of a `World` between two updates, see `World::snapshot`.
+///
+/// The observers and the recorded lineage are not a part of the state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorldSnapshot {
+    pub config: SimConfig,
+    pub seed: u64,
+    pub rng: SimRng,
+    pub tick: u64,
+    pub counters: Counters,
+    pub last_id: CellId,
+    /// Sorted by position
+    pub cells: Vec<(Position, Cell)>,
+    /// Cells added since the last update, sorted by position
+    pub pending: Vec<(Position, Cell)>,
+    /// Sorted
+    pub walls: Vec<Position>,
+    pub genotypes_first_seen: Vec<(GenotypeHash, u64)>,
+}
+
+impl WorldSnapshot {
+    pub fn write_to<W: Write>(&self, writer: W) -> io::Result<()> {
+        encode(writer, self)
+    }
+
+    pub fn read_from<R: Read>(reader: R) -> io::Result<Self> {
+        decode(reader)
+    }
+}
 
 pub struct World {
-    active_cells: HashMap<Position, Cell>,
+    /// Shared with the views and the stripes of the update, copied on write
+    active_cells: Arc<HashMap<Position, Cell>>,
     buffer: HashMap<Position, Cell>,
     width: i32,
     height: i32,
+    /// Indexed by `y * width + x`, shared like `active_cells`
+    walls: Arc<Vec<bool>>,
+    config: SimConfig,
+    seed: u64,
+    rng: SimRng,
+    tick: u64,
+    counters: Counters,
+    last_id: CellId,
+    record_lineage: bool,
+    lineage: Vec<LineageEvent>,
+    /// Shared with the views like `active_cells`
+    genotypes_first_seen: Arc<HashMap<GenotypeHash, u64>>,
+    /// With `Observer::wants_events`
+    observers: Vec<(ObserverId, bool, Box<dyn Observer>)>,
+    last_observer_id: ObserverId,
+    /// Runs the stripes of the update, see `set_threads`
+    pool: Option<WorkerPool>,
 }
 
 impl World {
-    pub fn new() -> Self {
+    pub fn new(config: SimConfig) -> Self {
+        let seed = config.seed.unwrap_or_else(rand::random);
+        Self {
+            active_cells: Arc::new(HashMap::new()),
+            buffer: HashMap::new(),
+            width: config.width,
+            height: config.height(),
+            walls: Arc::new(vec![false; config.area()]),
+            config,
+            seed,
+            rng: SimRng::seed_from_u64(seed),
+            tick: 0,
+            counters: Counters::default(),
+            last_id: NO_PARENT,
+            record_lineage: false,
+            lineage: Vec::new(),
+            genotypes_first_seen: Arc::default(),
+            observers: Vec::new(),
+            last_observer_id: 0,
+            pool: None,
+        }
+    }
+
+    /// Restores a world, it continues exactly like the one the snapshot was taken of.
+    /// An invalid config or mutation profile of a cell is `InvalidData`
+    pub fn from_snapshot(snapshot: WorldSnapshot) -> io::Result<Self> {
+        let config = snapshot.config;
+        let invalid = |e: ConfigError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        config.validate().map_err(invalid)?;
+        let (width, height) = (config.width, config.height());
+        let out_of_dish = || io::Error::new(io::ErrorKind::InvalidData, "cell outside the dish");
+        for (pos, cell) in snapshot.cells.iter().chain(&snapshot.pending) {
+            if !(1..width).contains(&pos.x()) || !(1..height).contains(&pos.y()) {
+                return Err(out_of_dish());
+            }
+            cell.mutation.validate().map_err(invalid)?;
+        }
+        if snapshot
+            .walls
+            .iter()
+            .any(|pos| !(0..width).contains(&pos.x()) || !(0..height).contains(&pos.y()))
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "wall outside the dish",
+            ));
+        }
+
+        let mut world = Self {
+            active_cells: Arc::new(snapshot.cells.into_iter().collect()),
+            buffer: snapshot.pending.into_iter().collect(),
+            width,
+            height,
+            walls: Arc::new(vec![false; config.area()]),
+            config,
+            seed: snapshot.seed,
+            rng: snapshot.rng,
+            tick: snapshot.tick,
+            counters: snapshot.counters,
+            last_id: snapshot.last_id,
+            record_lineage: false,
+            lineage: Vec::new(),
+            genotypes_first_seen: Arc::new(snapshot.genotypes_first_seen.into_iter().collect()),
+            observers: Vec::new(),
+            last_observer_id: 0,
+            pool: None,
+        };
+        for pos in snapshot.walls {
+            Arc::make_mut(&mut world.walls)[pos.to_index(width)] = true;
+        }
+        Ok(world)
+    }
+
+    /// Copy of the state for the readers on another thread, without the
+    /// observers and the lineage. The cells added since the last update are
+    /// active in the copy, so a paused world shows its edits
+    pub fn view(&self) -> Self {
+        let mut active_cells = Arc::clone(&self.active_cells);
+        if !self.buffer.is_empty() {
+            Arc::make_mut(&mut active_cells).extend(&self.buffer);
+        }
         Self {
-            active_cells: HashMap::new(),
+            active_cells,
             buffer: HashMap::new(),
-            width: WIDTH,
-            height: HEIGHT,
+            width: self.width,
+            height: self.height,
+            walls: Arc::clone(&self.walls),
+            config: self.config,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            tick: self.tick,
+            counters: self.counters,
+            last_id: self.last_id,
+            record_lineage: false,
+            lineage: Vec::new(),
+            genotypes_first_seen: Arc::clone(&self.genotypes_first_seen),
+            observers: Vec::new(),
+            last_observer_id: 0,
+            pool: None,
+        }
+    }
+
+    pub fn snapshot(&self) -> WorldSnapshot {
+        fn sorted<K: Copy + Ord, V: Copy>(map: &HashMap<K, V>) -> Vec<(K, V)> {
+            let mut items: Vec<(K, V)> = map.iter().map(|(k, v)| (*k, *v)).collect();
+            items.sort_unstable_by_key(|(k, _)| *k);
+            items
+        }
+
+        WorldSnapshot {
+            config: self.config,
+            seed: self.seed,
+            rng: self.rng.clone(),
+            tick: self.tick,
+            counters: self.counters,
+            last_id: self.last_id,
+            cells: sorted(&self.active_cells),
+            pending: sorted(&self.buffer),
+            walls: self.walls().collect(),
+            genotypes_first_seen: sorted(&self.genotypes_first_seen),
+        }
+    }
+
+    /// Number of finished updates
+    #[inline(always)]
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Seed of the random generator, the same seed and the same
+    /// sequence of calls give the same simulation
+    #[inline(always)]
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    #[inline(always)]
+    pub fn rng(&mut self) -> &mut SimRng {
+        &mut self.rng
+    }
+
+    /// A cell with the initial parameters of the config
+    pub fn new_cell(&mut self) -> Cell {
+        Cell::new(&self.config, &mut self.rng)
+    }
+
+    #[inline(always)]
+    pub fn counters(&self) -> &Counters {
+        &self.counters
+    }
+
+    #[inline(always)]
+    pub fn config(&self) -> &SimConfig {
+        &self.config
+    }
+
+    /// Sets the profile of the cells seeded from now on. `reset_living` also
+    /// gives it to every living cell, replacing their evolved profiles
+    pub fn set_mutation_profile(&mut self, profile: MutationProfile, reset_living: bool) {
+        self.config.mutation = profile;
+        if !reset_living {
+            return;
         }
+        Arc::make_mut(&mut self.active_cells)
+            .values_mut()
+            .chain(self.buffer.values_mut())
+            .for_each(|cell| cell.mutation = profile);
     }
 
+    #[inline(always)]
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline(always)]
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    /// Inside the dish and not a wall
     #[inline(always)]
     pub fn is_valid_pos(&self, pos: Position) -> bool {
-        pos.x() < self.width && pos.y() < self.height && pos.x() > 0 && pos.y() > 0
+        pos.x() < self.width
+            && pos.y() < self.height
+            && pos.x() > 0
+            && pos.y() > 0
+            && !self.walls[pos.to_index(self.width)]
+    }
+
+    #[inline(always)]
+    fn wall_index(&self, pos: Position) -> Option<usize> {
+        ((0..self.width).contains(&pos.x()) && (0..self.height).contains(&pos.y()))
+            .then(|| pos.to_index(self.width))
+    }
+
+    #[inline]
+    pub fn is_wall(&self, pos: Position) -> bool {
+        self.wall_index(pos).is_some_and(|index| self.walls[index])
+    }
+
+    /// A wall blocks the cells like the edge of the dish,
+    /// the cell under a new wall is removed
+    pub fn set_wall(&mut self, pos: Position, wall: bool) -> bool {
+        let Some(index) = self.wall_index(pos) else {
+            return false;
+        };
+        if wall {
+            self.del(pos);
+        }
+        Arc::make_mut(&mut self.walls)[index] = wall;
+        true
+    }
+
+    /// Sorted by position
+    pub fn walls(&self) -> impl Iterator<Item = Position> + '_ {
+        let width = self.width as usize;
+        self.walls
+            .iter()
+            .enumerate()
+            .filter(|(_, wall)| **wall)
+            .map(move |(index, _)| Position::new((index % width) as i32, (index / width) as i32))
     }
 
     #[inline(always)]
@@ -37,6 +354,26 @@ impl World {
         self.buffer.get_mut(&pos)
     }
 
+    /// The cell at `pos` between the updates, including the cells
+    /// added since the last update
+    pub fn cell(&self, pos: Position) -> Option<&Cell> {
+        self.buffer
+            .get(&pos)
+            .or_else(|| self.active_cells.get(&pos))
+    }
+
+    /// See `cell`, for the edits of the world between the updates
+    pub fn cell_mut(&mut self, pos: Position) -> Option<&mut Cell> {
+        match self.buffer.get_mut(&pos) {
+            Some(cell) => Some(cell),
+            // the shared cells are only copied for an existing cell
+            None if self.active_cells.contains_key(&pos) => {
+                Arc::make_mut(&mut self.active_cells).get_mut(&pos)
+            }
+            None => None,
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (&Position, &Cell)> {
         self.active_cells.iter()
     }
@@ -45,43 +382,659 @@ impl World {
         self.buffer.iter_mut()
     }
 
+    /// The living cells between the updates, including the cells added
+    /// since the last update
+    pub fn living(&self) -> impl Iterator<Item = &Cell> {
+        let active = self
+            .active_cells
+            .iter()
+            .filter(|(pos, cell)| self.buffer.get(pos).is_none_or(|added| added.id != cell.id));
+        self.buffer.values().chain(active.map(|(_, cell)| cell))
+    }
+
     pub fn count_cells(&self) -> usize {
         self.active_cells.len()
     }
 
+    /// Places a cell that already has an id, the new cells are added with `spawn`
+    ///
     /// true - added
     /// false dont added
-    pub fn add(&mut self, pos: Position, cell: Cell) -> bool {
-        self.with_valid_pos(pos, |buffer| buffer.insert(pos, cell).is_none())
+    pub(crate) fn add(&mut self, pos: Position, cell: Cell) -> bool {
+        let old = self.with_valid_pos(pos, |buffer| buffer.insert(pos, cell));
+        match old {
+            Some(Some(old)) => {
+                if old.id != cell.id {
+                    self.record_death(&old, pos, DeathCause::Displaced);
+                }
+                false
+            }
+            Some(None) => true,
+            None => false,
+        }
     }
 
+    /// Adds a cell with a new id, the cell founds its own clade
+    pub fn spawn(&mut self, pos: Position, mut cell: Cell) -> bool {
+        if !self.is_valid_pos(pos) {
+            return false;
+        }
+
+        cell.id = self.next_id();
+        cell.parent = NO_PARENT;
+        cell.clade = cell.id;
+        cell.birth_tick = self.tick;
+        self.counters.births += 1;
+        self.see_genotype(&cell);
+        self.record(LineageEvent::Birth {
+            tick: self.tick,
+            id: cell.id,
+            parent: NO_PARENT,
+            clade: cell.clade,
+        });
+        let tick = self.tick;
+        self.notify(|observer| observer.on_birth(tick, None, &cell, pos));
+
+        self.add(pos, cell)
+    }
+
+    /// Spawns the first cell of a new run at the centre of the dish
+    pub fn spawn_founder(&mut self) -> bool {
+        let cell = self.new_cell();
+        self.spawn(Position::new(self.width / 2, self.height / 2), cell)
+    }
+
+    /// Removes the cell at `pos` between the updates
+    ///
     /// true - del
     /// false - no del
     pub fn del(&mut self, pos: Position) -> bool {
-        self.with_valid_pos(pos, |buffer| buffer.remove(&pos).is_some())
+        if !self.is_valid_pos(pos) {
+            return false;
+        }
+
+        let active = self
+            .active_cells
+            .contains_key(&pos)
+            .then(|| Arc::make_mut(&mut self.active_cells).remove(&pos));
+        let removed = [self.buffer.remove(&pos), active.flatten()];
+        for old in removed.iter().flatten() {
+            self.record_death(old, pos, DeathCause::Removed);
+        }
+        removed.iter().any(Option::is_some)
     }
 
-    fn with_valid_pos<F>(&mut self, pos: Position, f: F) -> bool
+    fn with_valid_pos<F, T>(&mut self, pos: Position, f: F) -> Option<T>
     where
-        F: FnOnce(&mut HashMap<Position, Cell>) -> bool,
+        F: FnOnce(&mut HashMap<Position, Cell>) -> T,
     {
         if !self.is_valid_pos(pos) {
-            return false;
+            return None;
+        }
+        Some(f(&mut self.buffer))
+    }
+
+    #[inline(always)]
+    fn next_id(&mut self) -> CellId {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    /// Assigns the identity of a newborn placed at `pos`, a mutant founds a new clade
+    pub fn register_birth(
+        &mut self,
+        parent: &Cell,
+        child: &mut Cell,
+        pos: Position,
+        mutated: bool,
+    ) {
+        child.id = self.next_id();
+        child.parent = parent.id;
+        child.birth_tick = self.tick;
+        self.counters.births += 1;
+        if mutated {
+            child.clade = child.id;
+            self.counters.mutations += 1;
+            let (changed, junk) = count_mutated_loci(&parent.genome, &child.genome);
+            self.counters.mutated_loci += changed as u64;
+            self.counters.junk_mutated_loci += junk as u64;
+            self.see_genotype(child);
+        }
+
+        self.record(LineageEvent::Birth {
+            tick: self.tick,
+            id: child.id,
+            parent: parent.id,
+            clade: child.clade,
+        });
+        if mutated {
+            self.record(LineageEvent::Speciation {
+                tick: self.tick,
+                id: child.id,
+                parent_clade: parent.clade,
+                clade: child.clade,
+            });
+        }
+
+        let tick = self.tick;
+        let child = &*child;
+        self.notify(|observer| {
+            if mutated {
+                observer.on_mutation(tick, parent, child);
+            }
+            observer.on_birth(tick, Some(parent), child, pos);
+        });
+    }
+
+    #[inline(always)]
+    fn see_genotype(&mut self, cell: &Cell) {
+        let hash = cell.genome.genotype_hash();
+        // the shared history is only copied for a new genotype
+        if !self.genotypes_first_seen.contains_key(&hash) {
+            Arc::make_mut(&mut self.genotypes_first_seen).insert(hash, self.tick);
+        }
+    }
+
+    /// Tick when the genotype was first seen, it is forgotten
+    /// once the genotype dies out
+    pub fn genotype_first_seen(&self, hash: GenotypeHash) -> Option<u64> {
+        self.genotypes_first_seen.get(&hash).copied()
+    }
+
+    fn prune_genotypes(&mut self) {
+        let living: std::collections::HashSet<GenotypeHash> = self
+            .active_cells
+            .values()
+            .map(|cell| cell.genome.genotype_hash())
+            .collect();
+        Arc::make_mut(&mut self.genotypes_first_seen).retain(|hash, _| living.contains(hash));
+    }
+
+    #[inline(always)]
+    fn record_death(&mut self, cell: &Cell, pos: Position, cause: DeathCause) {
+        self.counters.deaths[cause as usize] += 1;
+        self.record(LineageEvent::Death {
+            tick: self.tick,
+            id: cell.id,
+            clade: cell.clade,
+            cause,
+        });
+        let tick = self.tick;
+        self.notify(|observer| observer.on_death(tick, cell, pos, cause));
+    }
+
+    pub fn add_observer(&mut self, observer: Box<dyn Observer>) -> ObserverId {
+        self.last_observer_id += 1;
+        let events = observer.wants_events();
+        self.observers
+            .push((self.last_observer_id, events, observer));
+        self.last_observer_id
+    }
+
+    pub fn remove_observer(&mut self, id: ObserverId) -> Option<Box<dyn Observer>> {
+        let index = self.observers.iter().position(|(i, ..)| *i == id)?;
+        Some(self.observers.remove(index).2)
+    }
+
+    #[inline(always)]
+    pub fn has_observers(&self) -> bool {
+        !self.observers.is_empty()
+    }
+
+    /// Some observer wants the events of the cells, see `Observer::wants_events`
+    #[inline]
+    pub fn has_event_observers(&self) -> bool {
+        self.observers.iter().any(|(_, events, _)| *events)
+    }
+
+    /// Calls `f` for every observer of the events of the cells, free when
+    /// there are none
+    #[inline(always)]
+    pub(crate) fn notify<F: FnMut(&mut dyn Observer)>(&mut self, mut f: F) {
+        for (_, events, observer) in self.observers.iter_mut() {
+            if *events {
+                f(observer.as_mut());
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn record(&mut self, event: LineageEvent) {
+        if self.record_lineage {
+            self.lineage.push(event);
+        }
+    }
+
+    pub fn set_lineage_recording(&mut self, enabled: bool) {
+        self.record_lineage = enabled;
+        if !enabled {
+            self.lineage.clear();
+        }
+    }
+
+    /// Takes the lineage events recorded since the last call
+    pub fn drain_lineage(&mut self) -> std::vec::Drain<'_, LineageEvent> {
+        self.lineage.drain(..)
+    }
+
+    /// Workers of the striped update, see `update`. Ignored without
+    /// `SimConfig::stripe_height`, the number does not change the result
+    pub fn set_threads(&mut self, threads: usize) -> io::Result<()> {
+        self.pool = None;
+        // the stripes of one half run at once
+        let threads = threads.min(self.count_stripes().div_ceil(2));
+        if threads > 1 {
+            self.pool = Some(WorkerPool::new(threads)?);
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn threads(&self) -> usize {
+        self.pool.as_ref().map_or(1, WorkerPool::threads)
+    }
+
+    /// `0` without `SimConfig::stripe_height`
+    fn count_stripes(&self) -> usize {
+        match self.config.stripe_height {
+            0 => 0,
+            rows => (self.height as usize).div_ceil(rows as usize),
         }
-        f(&mut self.buffer)
     }
 
+    /// Runs one tick.
+    ///
+    /// The cells are updated in the order of their positions and each one
+    /// sees the changes made before it. With `SimConfig::stripe_height` the
+    /// rows are split into stripes, updated the same way: the even stripes
+    /// first, then the odd ones. A cell changes only the rows within
+    /// `INTERACTION_RADIUS`, so the stripes of a half never touch the same
+    /// cells and run at once on the threads of `set_threads`. Every stripe
+    /// has its own generator seeded from the one of the world, the ticks
+    /// depend on the seed and the stripes but not on the threads.
     pub fn update(&mut self) {
-        let mut poss: Vec<Position> = self.active_cells.keys().cloned().collect();
-        for pos in poss.iter_mut() {
-            let mut cell = *self.get(*pos).unwrap();
-            cell.update(pos, self);
+        if self.config.stripe_height > 0 {
+            self.update_stripes();
+        } else {
+            let mut poss: Vec<Position> = self.active_cells.keys().cloned().collect();
+            // the order of a `HashMap` differs between runs
+            poss.sort_unstable();
+            for pos in poss {
+                self.update_cell(pos);
+            }
+        }
+
+        self.active_cells = Arc::new(std::mem::take(&mut self.buffer));
+        self.tick += 1;
+
+        if self.tick.is_multiple_of(GENOTYPE_PRUNE_INTERVAL) {
+            self.prune_genotypes();
+        }
+
+        if self.has_observers() {
+            let mut observers = std::mem::take(&mut self.observers);
+            observers
+                .iter_mut()
+                .for_each(|(.., observer)| observer.on_tick(self));
+            self.observers = observers;
+        }
+    }
+
+    /// Updates the cell that was at `from` at the start of the tick
+    fn update_cell(&mut self, from: Position) {
+        let mut to = from;
+        let mut cell = *self.get(from).unwrap();
+        let gene = *cell.genome.get();
+        cell.update(&mut to, self);
+        cell.last_gene = Some(gene);
+
+        let tick = self.tick;
+        self.notify(|observer| {
+            if from != to {
+                observer.on_move(tick, &cell, from, to);
+            }
+            observer.on_action(tick, &cell, from, to, gene);
+        });
 
-            if cell.is_alive() {
-                self.add(*pos, cell);
+        match cell.death_cause(&self.config.cell) {
+            None => {
+                self.add(to, cell);
             }
+            Some(cause) => self.record_death(&cell, to, cause),
         }
+    }
+
+    fn update_stripes(&mut self) {
+        let rows = self.config.stripe_height;
+        let mut cells = vec![Vec::new(); self.count_stripes()];
+        for pos in self.active_cells.keys() {
+            cells[(pos.y() / rows) as usize].push(*pos);
+        }
+
+        // every cell has at most one child per tick, the ids are reserved
+        // for each stripe in advance
+        let mut halves = [Vec::new(), Vec::new()];
+        for (index, mut cells) in cells.into_iter().enumerate() {
+            cells.sort_unstable();
+            let stripe = self.stripe();
+            let last_id = self.last_id;
+            self.last_id += cells.len() as CellId;
+            halves[index % 2].push((cells, stripe, last_id));
+        }
+
+        // the cells added since the last tick, then the ones the other half
+        // placed, are moved into the stripe reaching their row
+        let mut pending = std::mem::take(&mut self.buffer);
+        let mut placed: Vec<HashMap<Position, Cell>> = Vec::with_capacity(halves[0].len() * 2);
+        for (parity, half) in halves.into_iter().enumerate() {
+            let reaching = |pos: &Position| {
+                [pos.y() - INTERACTION_RADIUS, pos.y() + INTERACTION_RADIUS]
+                    .map(|y| y.div_euclid(rows))
+                    .into_iter()
+                    .find(|index| *index >= 0 && index % 2 == parity as i32)
+                    .map(|index| index as usize / 2)
+                    .filter(|index| *index < half.len())
+            };
+            let mut buffers: Vec<HashMap<Position, Cell>> = half
+                .iter()
+                .map(|(cells, ..)| HashMap::with_capacity(cells.len()))
+                .collect();
+            let moved = pending.extract_if(|pos, _| reaching(pos).is_some()).chain(
+                placed
+                    .iter_mut()
+                    .flat_map(|buffer| buffer.extract_if(|pos, _| reaching(pos).is_some())),
+            );
+            for (pos, cell) in moved {
+                buffers[reaching(&pos).unwrap()].insert(pos, cell);
+            }
+
+            let mut logs = Vec::with_capacity(half.len());
+            let jobs: Vec<_> = half
+                .into_iter()
+                .zip(buffers)
+                .map(|((cells, mut stripe, last_id), buffer)| {
+                    stripe.last_id = last_id;
+                    stripe.buffer = buffer;
+                    let log = self
+                        .has_event_observers()
+                        .then(|| Arc::new(Mutex::new(EventLog::new())));
+                    if let Some(log) = &log {
+                        stripe.add_observer(Box::new(Arc::clone(log)));
+                    }
+                    logs.push(log);
+
+                    move || {
+                        for pos in cells {
+                            stripe.update_cell(pos);
+                        }
+                        stripe
+                    }
+                })
+                .collect();
+            let stripes = match &self.pool {
+                Some(pool) => pool.run(jobs),
+                None => jobs.into_iter().map(|job| job()).collect(),
+            };
+
+            // in the order of the stripes
+            for (mut stripe, log) in stripes.into_iter().zip(logs) {
+                placed.push(std::mem::take(&mut stripe.buffer));
+                self.merge(stripe, log);
+            }
+        }
+
+        self.buffer = pending;
+        self.buffer
+            .reserve(placed.iter().map(HashMap::len).sum::<usize>());
+        for buffer in placed {
+            self.buffer.extend(buffer);
+        }
+    }
+
+    /// Empty world of a stripe, sharing the cells and the walls
+    fn stripe(&mut self) -> Self {
+        Self {
+            active_cells: Arc::clone(&self.active_cells),
+            buffer: HashMap::new(),
+            width: self.width,
+            height: self.height,
+            walls: Arc::clone(&self.walls),
+            config: self.config,
+            seed: self.seed,
+            rng: SimRng::seed_from_u64(self.rng.next_u64()),
+            tick: self.tick,
+            counters: Counters::default(),
+            last_id: self.last_id,
+            record_lineage: self.record_lineage,
+            lineage: Vec::new(),
+            genotypes_first_seen: Arc::default(),
+            observers: Vec::new(),
+            last_observer_id: 0,
+            pool: None,
+        }
+    }
+
+    /// Everything but the cells
+    fn merge(&mut self, stripe: Self, log: Option<Arc<Mutex<EventLog>>>) {
+        self.counters += stripe.counters;
+        self.lineage.extend(stripe.lineage);
+        if !stripe.genotypes_first_seen.is_empty() {
+            let seen = Arc::make_mut(&mut self.genotypes_first_seen);
+            for (&hash, &tick) in stripe.genotypes_first_seen.iter() {
+                seen.entry(hash).or_insert(tick);
+            }
+        }
+        if let Some(log) = log {
+            for event in log.lock().unwrap().drain() {
+                self.notify(|observer| event.replay(observer));
+            }
+        }
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new(SimConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{genome::Gene, testing};
+
+    fn populated(stripe_height: i32, threads: usize) -> World {
+        let config = SimConfig {
+            seed: Some(42),
+            radius_petri_dish: 30,
+            width: 120,
+            stripe_height,
+            ..SimConfig::default()
+        };
+        let mut world = World::new(config);
+        world.set_threads(threads).unwrap();
+        testing::populate(&mut world, 6);
+        world
+    }
+
+    fn run(mut world: World, ticks: u64) -> Vec<u8> {
+        for _ in 0..ticks {
+            world.update();
+        }
+        assert!(world.count_cells() > 0, "the population died out");
+        testing::state(&world)
+    }
+
+    #[test]
+    fn sequential_update_is_deterministic() {
+        assert_eq!(run(populated(0, 1), 200), run(populated(0, 1), 200));
+    }
+
+    #[test]
+    fn snapshot_continues_the_run() {
+        let mut world = populated(0, 1);
+        for _ in 0..100 {
+            world.update();
+        }
+        let bytes = testing::state(&world);
+        let restored =
+            World::from_snapshot(WorldSnapshot::read_from(bytes.as_slice()).unwrap()).unwrap();
+        assert_eq!(restored.tick(), world.tick());
+        assert_eq!(run(restored, 100), run(world, 100));
+    }
+
+    #[test]
+    fn view_shows_the_cells_added_while_paused() {
+        let mut world = populated(0, 1);
+        world.update();
+        let active = world.count_cells();
+        world.spawn_founder();
+        let center = Position::new(world.width() / 2, world.height() / 2);
+
+        let view = world.view();
+        assert_eq!(view.count_cells(), active + 1);
+        assert!(view.get(center).is_some());
+        // the copy leaves the cells of the world pending
+        assert_eq!(world.count_cells(), active);
+    }
+
+    #[test]
+    fn profile_reset_reaches_the_living_cells() {
+        let mut world = populated(0, 1);
+        world.update();
+        let profile = MutationProfile {
+            cell: 0.25,
+            ..MutationProfile::default()
+        };
+        world.set_mutation_profile(profile, false);
+        assert!(world.iter().all(|(_, cell)| cell.mutation.cell != 0.25));
+        assert_eq!(world.new_cell().mutation, profile);
+
+        world.set_mutation_profile(profile, true);
+        assert!(world.iter().all(|(_, cell)| cell.mutation == profile));
+    }
+
+    #[test]
+    fn invalid_snapshots_are_rejected() {
+        let world = populated(0, 1);
+        let mut snapshot = world.snapshot();
+        snapshot.pending[0].1.mutation.cell = f64::NAN;
+        let e = World::from_snapshot(snapshot).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let mut snapshot = world.snapshot();
+        snapshot.config.width = 0;
+        assert!(World::from_snapshot(snapshot).is_err());
+    }
+
+    #[test]
+    fn snapshot_positions_must_be_in_the_dish() {
+        let mut world = populated(0, 1);
+        world.update();
+        let (width, height) = (world.width(), world.height());
+        for pos in [
+            Position::new(0, 5),
+            Position::new(width, 5),
+            Position::new(5, height),
+            Position::new(5, -1),
+        ] {
+            let mut snapshot = world.snapshot();
+            snapshot.cells[0].0 = pos;
+            let e = World::from_snapshot(snapshot).err().unwrap();
+            assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+            let mut snapshot = world.snapshot();
+            let cell = snapshot.cells[0].1;
+            snapshot.pending.push((pos, cell));
+            assert!(World::from_snapshot(snapshot).is_err());
+        }
+
+        let mut snapshot = world.snapshot();
+        snapshot.walls.push(Position::new(width, 0));
+        assert!(World::from_snapshot(snapshot).is_err());
+        let mut snapshot = world.snapshot();
+        snapshot.walls.push(Position::new(width - 1, 0));
+        let restored = World::from_snapshot(snapshot).unwrap();
+        assert!(restored.is_wall(Position::new(width - 1, 0)));
+    }
+
+    #[test]
+    fn founder_is_at_the_centre() {
+        let mut world = World::new(SimConfig::default());
+        assert!(world.spawn_founder());
+        let centre = Position::new(world.width() / 2, world.height() / 2);
+        assert_eq!(world.cell(centre).map(|cell| cell.id), Some(1));
+        assert_eq!(world.living().count(), 1);
+    }
+
+    #[test]
+    fn striped_update_does_not_depend_on_threads() {
+        let single = populated(8, 1);
+        assert_eq!(single.threads(), 1);
+        let parallel = populated(8, 4);
+        assert_eq!(parallel.threads(), 4);
+
+        let expected = run(single, 300);
+        assert_eq!(run(parallel, 300), expected);
+        assert_eq!(run(populated(8, 3), 300), expected);
+    }
+
+    #[test]
+    fn striped_update_replays_events_in_order() {
+        fn events(threads: usize) -> Vec<String> {
+            let mut world = populated(8, threads);
+            let log = Arc::new(Mutex::new(EventLog::new()));
+            world.add_observer(Box::new(Arc::clone(&log)));
+            for _ in 0..50 {
+                world.update();
+            }
+            let mut log = log.lock().unwrap();
+            log.drain().map(|event| format!("{:?}", event)).collect()
+        }
+
+        let single = events(1);
+        assert!(!single.is_empty());
+        assert_eq!(events(4), single);
+    }
+
+    #[test]
+    fn observers_without_events_are_only_ticked() {
+        #[derive(Default)]
+        struct Ticks {
+            ticks: u64,
+            events: u64,
+        }
+        impl Observer for Ticks {
+            fn on_action(&mut self, _: u64, _: &Cell, _: Position, _: Position, _: Gene) {
+                self.events += 1;
+            }
+            fn on_tick(&mut self, _world: &World) {
+                self.ticks += 1;
+            }
+            fn wants_events(&self) -> bool {
+                false
+            }
+        }
+
+        let mut world = populated(8, 2);
+        let ticks = Arc::new(Mutex::new(Ticks::default()));
+        world.add_observer(Box::new(Arc::clone(&ticks)));
+        assert!(world.has_observers());
+        assert!(!world.has_event_observers());
+        for _ in 0..10 {
+            world.update();
+        }
+        let ticks = ticks.lock().unwrap();
+        assert_eq!((ticks.ticks, ticks.events), (10, 0));
+    }
 
-        self.active_cells = std::mem::take(&mut self.buffer);
+    #[test]
+    fn threads_are_bounded_by_the_stripes() {
+        // 60 rows in 2 stripes, one of each half
+        let mut world = populated(30, 8);
+        assert_eq!(world.threads(), 1);
+        world.set_threads(1).unwrap();
+        assert_eq!(world.threads(), 1);
+        assert_eq!(populated(0, 8).threads(), 1);
     }
 }